@@ -4,7 +4,7 @@
 #[cfg(test)]
 mod tests;
 
-use std::mem::take;
+use std::{mem::take, rc::Rc};
 
 use miette::Diagnostic;
 use qsc_data_structures::span::Span;
@@ -19,14 +19,14 @@ use qsc_hir::{
     ty::Ty,
     visit::{self, Visitor},
 };
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use thiserror::Error;
 
 use crate::{
     common::generated_name,
     id_update::NodeIdRefresher,
     invert_block::adj_invert_block,
-    spec_gen::adj_gen::{self, AdjDistrib},
+    spec_gen::adj_gen::{self, AdjDistrib, AdjResolver},
 };
 
 #[derive(Clone, Debug, Diagnostic, Error)]
@@ -35,81 +35,130 @@ pub enum Error {
     #[error(transparent)]
     AdjGen(adj_gen::Error),
 
+    #[error("variable `{0}` cannot be assigned in apply-block since it is used in within-block")]
+    #[diagnostic(help("updating mutable variables in the apply-block that are used in the within-block can violate logic reversibility"))]
+    #[diagnostic(code("Qsc.ConjugateInvert.ApplyAssign"))]
+    ApplyAssign(Rc<str>, #[label] Span),
+
     #[error("variable cannot be assigned in apply-block since it is used in within-block")]
     #[diagnostic(help("updating mutable variables in the apply-block that are used in the within-block can violate logic reversibility"))]
     #[diagnostic(code("Qsc.ConjugateInvert.ApplyAssign"))]
-    ApplyAssign(#[label] Span),
+    ApplyAssignUnnamed(#[label] Span),
 
     #[error("return expressions are not allowed in apply-blocks")]
     #[diagnostic(code("Qsc.ConjugateInvert.ReturnForbidden"))]
     ReturnForbidden(#[label] Span),
 }
 
+/// Controls whether `invert_conjugate_exprs` keeps the transformed package.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum InversionMode {
+    /// Replace the conjugate expressions in the package with their inverted blocks.
+    Transform,
+    /// Perform the full inversion on a clone of the package to report its errors, leaving
+    /// the package untouched. Node ids are still drawn from the assigner.
+    DryRun,
+}
+
 /// Generates adjoint inverted blocks for within-blocks across all conjugate expressions,
 /// eliminating the conjugate expression from the compilation unit.
+///
+/// If a `resolver` is given, it is consulted to choose the adjoint of each operation called
+/// in the inverted within-blocks, before falling back to the `Adjoint` functor.
 pub(super) fn invert_conjugate_exprs(
     core: &Table,
     package: &mut Package,
     assigner: &mut Assigner,
+    mode: InversionMode,
+    resolver: Option<&AdjResolver<'_>>,
 ) -> Vec<Error> {
     let mut pass = ConjugateElim {
         core,
         assigner,
+        resolver,
+        names: FxHashMap::default(),
         errors: Vec::new(),
     };
-    pass.visit_package(package);
+    match mode {
+        InversionMode::Transform => pass.visit_package(package),
+        InversionMode::DryRun => pass.visit_package(&mut package.clone()),
+    }
     pass.errors
 }
 
+/// Replaces `block` with its adjoint inverse: the quantum statements are reversed and the
+/// adjoint is distributed over the operation calls, with fresh node ids.
+///
+/// Fails if the classical and quantum statements of `block` can't be separated, in which case
+/// `block` is left untouched. Otherwise, returns the errors of distributing the adjoint.
+pub(super) fn invert_block(
+    core: &Table,
+    assigner: &mut Assigner,
+    block: &mut Block,
+    resolver: Option<&AdjResolver<'_>>,
+) -> Result<Vec<Error>, Vec<Error>> {
+    adj_invert_block(core, assigner, block).map_err(|invert_errors| {
+        invert_errors
+            .into_iter()
+            .map(adj_gen::Error::LogicSep)
+            .map(Error::AdjGen)
+            .collect::<Vec<_>>()
+    })?;
+    let mut distrib = AdjDistrib {
+        errors: Vec::new(),
+        resolver,
+    };
+    distrib.visit_block(block);
+    NodeIdRefresher::new(assigner).visit_block(block);
+    Ok(distrib.errors.into_iter().map(Error::AdjGen).collect())
+}
+
 struct ConjugateElim<'a> {
     core: &'a Table,
     assigner: &'a mut Assigner,
+    resolver: Option<&'a AdjResolver<'a>>,
+    /// Names of the local variables bound so far, used in diagnostics.
+    names: FxHashMap<NodeId, Rc<str>>,
     errors: Vec<Error>,
 }
 
 impl<'a> MutVisitor for ConjugateElim<'a> {
+    fn visit_pat(&mut self, pat: &mut Pat) {
+        if let PatKind::Bind(ident) = &pat.kind {
+            self.names.insert(ident.id, ident.name.clone());
+        }
+        mut_visit::walk_pat(self, pat);
+    }
+
     fn visit_expr(&mut self, expr: &mut Expr) {
         match take(&mut expr.kind) {
+            ExprKind::Conjugate(within, apply) if within.stmts.is_empty() => {
+                // There is nothing to invert, so the conjugate reduces to its apply-block.
+                self.check_apply_block(&within, &apply);
+                expr.kind = ExprKind::Block(apply);
+            }
             ExprKind::Conjugate(within, apply) => {
-                let mut usage = Usage {
-                    used: FxHashSet::default(),
-                };
-                usage.visit_block(&within);
-                let mut assign_check = AssignmentCheck {
-                    used: usage.used,
-                    errors: Vec::new(),
-                };
-                assign_check.visit_block(&apply);
-                self.errors.extend(assign_check.errors);
-
-                let mut return_check = ReturnCheck { errors: Vec::new() };
-                return_check.visit_block(&apply);
-                self.errors.extend(return_check.errors);
+                self.check_apply_block(&within, &apply);
 
                 let mut adj_within = within.clone();
-                if let Err(invert_errors) =
-                    adj_invert_block(self.core, self.assigner, &mut adj_within)
-                {
-                    self.errors.extend(
-                        invert_errors
-                            .into_iter()
-                            .map(adj_gen::Error::LogicSep)
-                            .map(Error::AdjGen),
-                    );
-                    return;
+                match invert_block(self.core, self.assigner, &mut adj_within, self.resolver) {
+                    Ok(distrib_errors) => self.errors.extend(distrib_errors),
+                    Err(invert_errors) => {
+                        self.errors.extend(invert_errors);
+                        return;
+                    }
                 }
-                let mut distrib = AdjDistrib { errors: Vec::new() };
-                distrib.visit_block(&mut adj_within);
-                self.errors
-                    .extend(distrib.errors.into_iter().map(Error::AdjGen));
-
-                NodeIdRefresher::new(self.assigner).visit_block(&mut adj_within);
 
+                // The generated expressions and bindings take the spans of the blocks they wrap,
+                // so that later diagnostics point back at the conjugate in the source. The
+                // generated statements keep default spans, which mark them as generated code
+                // for the debugger and keep breakpoints off them.
+                let apply_span = apply.span;
                 let (bind_id, apply_as_bind) = self.block_as_binding(apply, expr.ty.clone());
 
                 let new_block = Block {
                     id: self.assigner.next_node(),
-                    span: Span::default(),
+                    span: expr.span,
                     ty: expr.ty.clone(),
                     stmts: vec![
                         self.block_as_stmt(within),
@@ -120,7 +169,7 @@ impl<'a> MutVisitor for ConjugateElim<'a> {
                             span: Span::default(),
                             kind: StmtKind::Expr(Expr {
                                 id: self.assigner.next_node(),
-                                span: Span::default(),
+                                span: apply_span,
                                 ty: expr.ty.clone(),
                                 kind: ExprKind::Var(Res::Local(bind_id), Vec::new()),
                             }),
@@ -137,10 +186,28 @@ impl<'a> MutVisitor for ConjugateElim<'a> {
 }
 
 impl ConjugateElim<'_> {
+    fn check_apply_block(&mut self, within: &Block, apply: &Block) {
+        let mut usage = Usage {
+            used: FxHashSet::default(),
+        };
+        usage.visit_block(within);
+        let mut assign_check = AssignmentCheck {
+            used: usage.used,
+            names: &self.names,
+            errors: Vec::new(),
+        };
+        assign_check.visit_block(apply);
+        self.errors.extend(assign_check.errors);
+
+        let mut return_check = ReturnCheck { errors: Vec::new() };
+        return_check.visit_block(apply);
+        self.errors.extend(return_check.errors);
+    }
+
     fn block_as_expr(&mut self, block: Block, ty: Ty) -> Expr {
         Expr {
             id: self.assigner.next_node(),
-            span: Span::default(),
+            span: block.span,
             ty,
             kind: ExprKind::Block(block),
         }
@@ -155,6 +222,7 @@ impl ConjugateElim<'_> {
     }
     fn block_as_binding(&mut self, block: Block, ty: Ty) -> (NodeId, Stmt) {
         let bind_id = self.assigner.next_node();
+        let span = block.span;
         (
             bind_id,
             Stmt {
@@ -164,11 +232,11 @@ impl ConjugateElim<'_> {
                     Mutability::Immutable,
                     Pat {
                         id: self.assigner.next_node(),
-                        span: Span::default(),
+                        span,
                         ty: ty.clone(),
                         kind: PatKind::Bind(Ident {
                             id: bind_id,
-                            span: Span::default(),
+                            span,
                             name: generated_name("apply_res"),
                         }),
                     },
@@ -194,30 +262,47 @@ impl<'a> Visitor<'a> for Usage {
     }
 }
 
-struct AssignmentCheck {
+struct AssignmentCheck<'b> {
     used: FxHashSet<NodeId>,
+    names: &'b FxHashMap<NodeId, Rc<str>>,
     errors: Vec<Error>,
 }
 
-impl<'a> Visitor<'a> for AssignmentCheck {
+impl<'a> Visitor<'a> for AssignmentCheck<'_> {
     fn visit_expr(&mut self, expr: &'a Expr) {
         match &expr.kind {
             ExprKind::Assign(lhs, rhs) => {
                 self.visit_expr(rhs);
                 self.check_assign(lhs);
             }
+            ExprKind::AssignOp(_, lhs, rhs) => {
+                self.visit_expr(rhs);
+                self.check_assign(lhs);
+            }
+            ExprKind::AssignField(record, _, replace) => {
+                self.visit_expr(replace);
+                self.check_assign(record);
+            }
+            ExprKind::AssignIndex(container, index, replace) => {
+                self.visit_expr(index);
+                self.visit_expr(replace);
+                self.check_assign(container);
+            }
             _ => visit::walk_expr(self, expr),
         }
     }
 }
 
-impl AssignmentCheck {
+impl AssignmentCheck<'_> {
     fn check_assign(&mut self, expr: &Expr) {
         match &expr.kind {
             ExprKind::Hole => {}
             ExprKind::Var(Res::Local(id), _) => {
                 if self.used.contains(id) {
-                    self.errors.push(Error::ApplyAssign(expr.span));
+                    self.errors.push(match self.names.get(id) {
+                        Some(name) => Error::ApplyAssign(name.clone(), expr.span),
+                        None => Error::ApplyAssignUnnamed(expr.span),
+                    });
                 }
             }
             ExprKind::Tuple(var_tup) => {
@@ -225,7 +310,10 @@ impl AssignmentCheck {
                     self.check_assign(expr);
                 }
             }
-            _ => panic!("unexpected expr type in assignment"),
+            // Assigning to an element or a field mutates the local at the root of the target.
+            ExprKind::Field(inner, _) | ExprKind::Index(inner, _) => self.check_assign(inner),
+            // Any other target is not assignable, which is reported by the borrow checker.
+            _ => {}
         }
     }
 }
@@ -236,8 +324,12 @@ struct ReturnCheck {
 
 impl<'a> Visitor<'a> for ReturnCheck {
     fn visit_expr(&mut self, expr: &'a Expr) {
+        // Lambdas are lifted to separate callables and nested callables are items, so neither
+        // of their bodies is visited here; a return inside them doesn't leave the apply-block.
         if matches!(&expr.kind, ExprKind::Return(..)) {
             self.errors.push(Error::ReturnForbidden(expr.span));
+        } else {
+            visit::walk_expr(self, expr);
         }
     }
 }
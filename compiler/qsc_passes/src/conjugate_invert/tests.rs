@@ -5,25 +5,48 @@
 
 use expect_test::{expect, Expect};
 use indoc::indoc;
-use qsc_data_structures::{language_features::LanguageFeatures, target::TargetCapabilityFlags};
-use qsc_frontend::compile::{self, compile, PackageStore, SourceMap};
-use qsc_hir::{validate::Validator, visit::Visitor};
+use qsc_data_structures::{
+    language_features::LanguageFeatures, span::Span, target::TargetCapabilityFlags,
+};
+use qsc_frontend::compile::{self, compile, CompileUnit, PackageStore, SourceMap};
+use qsc_hir::{
+    hir::{
+        Block, Expr, ExprKind, Functor, ItemId, ItemKind, Lit, NodeId, Package, Pat, Res, SpecBody,
+        Stmt, StmtKind, UnOp,
+    },
+    validate::Validator,
+    visit::{self, Visitor},
+};
 
-use crate::conjugate_invert::invert_conjugate_exprs;
+use crate::{
+    conjugate_invert::{invert_conjugate_exprs, InversionMode},
+    invert_block_for_stmt, invert_conjugate_exprs_with_resolver,
+};
 
-fn check(file: &str, expect: &Expect) {
-    let store = PackageStore::new(compile::core());
-    let sources = SourceMap::new([("test".into(), file.into())], None);
-    let mut unit = compile(
-        &store,
+fn compile_unit(store: &PackageStore, source: &str) -> CompileUnit {
+    let sources = SourceMap::new([("test".into(), source.into())], None);
+    let unit = compile(
+        store,
         &[],
         sources,
         TargetCapabilityFlags::all(),
         LanguageFeatures::default(),
     );
     assert!(unit.errors.is_empty(), "{:?}", unit.errors);
+    unit
+}
 
-    let errors = invert_conjugate_exprs(store.core(), &mut unit.package, &mut unit.assigner);
+fn check(file: &str, expect: &Expect) {
+    let store = PackageStore::new(compile::core());
+    let mut unit = compile_unit(&store, file);
+
+    let errors = invert_conjugate_exprs(
+        store.core(),
+        &mut unit.package,
+        &mut unit.assigner,
+        InversionMode::Transform,
+        None,
+    );
     Validator::default().visit_package(&unit.package);
     if errors.is_empty() {
         expect.assert_eq(&unit.package.to_string());
@@ -32,6 +55,22 @@ fn check(file: &str, expect: &Expect) {
     }
 }
 
+fn check_dry_run(file: &str, expect: &Expect) {
+    let store = PackageStore::new(compile::core());
+    let mut unit = compile_unit(&store, file);
+
+    let package_before = unit.package.to_string();
+    let errors = invert_conjugate_exprs(
+        store.core(),
+        &mut unit.package,
+        &mut unit.assigner,
+        InversionMode::DryRun,
+        None,
+    );
+    assert_eq!(package_before, unit.package.to_string());
+    expect.assert_debug_eq(&errors);
+}
+
 #[test]
 fn conjugate_invert() {
     check(
@@ -75,8 +114,8 @@ fn conjugate_invert() {
                         functors: empty set
                         body: SpecDecl 9 [63-216]: Impl:
                             Block 10 [84-216] [Type Unit]:
-                                Stmt 11 [94-210]: Expr: Expr 54 [0-0] [Type Unit]: Expr Block: Block 47 [0-0] [Type Unit]:
-                                    Stmt 48 [0-0]: Expr: Expr 49 [0-0] [Type Unit]: Expr Block: Block 13 [101-148] [Type Unit]:
+                                Stmt 11 [94-210]: Expr: Expr 54 [94-210] [Type Unit]: Expr Block: Block 47 [94-210] [Type Unit]:
+                                    Stmt 48 [0-0]: Expr: Expr 49 [101-148] [Type Unit]: Expr Block: Block 13 [101-148] [Type Unit]:
                                         Stmt 14 [115-120]: Semi: Expr 15 [115-119] [Type Unit]: Call:
                                             Expr 16 [115-116] [Type (Int => Unit is Adj)]: Var: Item 1
                                             Expr 17 [117-118] [Type Int]: Lit: Int(1)
@@ -84,15 +123,15 @@ fn conjugate_invert() {
                                             Expr 20 [133-134] [Type (Int => Unit is Adj)]: Var: Item 1
                                             Expr 21 [135-136] [Type Int]: Lit: Int(2)
                                     Stmt 44 [0-0]: Local (Immutable):
-                                        Pat 45 [0-0] [Type Unit]: Bind: Ident 43 [0-0] "@apply_res"
-                                        Expr 46 [0-0] [Type Unit]: Expr Block: Block 22 [163-210] [Type Unit]:
+                                        Pat 45 [163-210] [Type Unit]: Bind: Ident 43 [163-210] "@apply_res"
+                                        Expr 46 [163-210] [Type Unit]: Expr Block: Block 22 [163-210] [Type Unit]:
                                             Stmt 23 [177-182]: Semi: Expr 24 [177-181] [Type Unit]: Call:
                                                 Expr 25 [177-178] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 26 [179-180] [Type Int]: Lit: Int(3)
                                             Stmt 27 [195-200]: Semi: Expr 28 [195-199] [Type Unit]: Call:
                                                 Expr 29 [195-196] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 30 [197-198] [Type Int]: Lit: Int(4)
-                                    Stmt 50 [0-0]: Expr: Expr 51 [0-0] [Type Unit]: Expr Block: Block 32 [101-148] [Type Unit]:
+                                    Stmt 50 [0-0]: Expr: Expr 51 [101-148] [Type Unit]: Expr Block: Block 32 [101-148] [Type Unit]:
                                         Stmt 33 [133-138]: Semi: Expr 34 [133-137] [Type Unit]: Call:
                                             Expr 35 [133-134] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                 Expr 36 [133-134] [Type (Int => Unit is Adj)]: Var: Item 1
@@ -101,7 +140,7 @@ fn conjugate_invert() {
                                             Expr 40 [115-116] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                 Expr 41 [115-116] [Type (Int => Unit is Adj)]: Var: Item 1
                                             Expr 42 [117-118] [Type Int]: Lit: Int(1)
-                                    Stmt 52 [0-0]: Expr: Expr 53 [0-0] [Type Unit]: Var: Local 43
+                                    Stmt 52 [0-0]: Expr: Expr 53 [163-210] [Type Unit]: Var: Local 43
                         adj: <none>
                         ctl: <none>
                         ctl-adj: <none>"#]],
@@ -155,8 +194,8 @@ fn conjugate_invert_with_output() {
                             Block 10 [83-252] [Type Int]:
                                 Stmt 11 [93-234]: Local (Immutable):
                                     Pat 12 [97-100] [Type Int]: Bind: Ident 13 [97-100] "val"
-                                    Expr 60 [0-0] [Type Int]: Expr Block: Block 53 [0-0] [Type Int]:
-                                        Stmt 54 [0-0]: Expr: Expr 55 [0-0] [Type Unit]: Expr Block: Block 15 [110-157] [Type Unit]:
+                                    Expr 60 [103-233] [Type Int]: Expr Block: Block 53 [103-233] [Type Int]:
+                                        Stmt 54 [0-0]: Expr: Expr 55 [110-157] [Type Unit]: Expr Block: Block 15 [110-157] [Type Unit]:
                                             Stmt 16 [124-129]: Semi: Expr 17 [124-128] [Type Unit]: Call:
                                                 Expr 18 [124-125] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 19 [126-127] [Type Int]: Lit: Int(1)
@@ -164,8 +203,8 @@ fn conjugate_invert_with_output() {
                                                 Expr 22 [142-143] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 23 [144-145] [Type Int]: Lit: Int(2)
                                         Stmt 50 [0-0]: Local (Immutable):
-                                            Pat 51 [0-0] [Type Int]: Bind: Ident 49 [0-0] "@apply_res"
-                                            Expr 52 [0-0] [Type Int]: Expr Block: Block 24 [172-233] [Type Int]:
+                                            Pat 51 [172-233] [Type Int]: Bind: Ident 49 [172-233] "@apply_res"
+                                            Expr 52 [172-233] [Type Int]: Expr Block: Block 24 [172-233] [Type Int]:
                                                 Stmt 25 [186-191]: Semi: Expr 26 [186-190] [Type Unit]: Call:
                                                     Expr 27 [186-187] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 28 [188-189] [Type Int]: Lit: Int(3)
@@ -173,7 +212,7 @@ fn conjugate_invert_with_output() {
                                                     Expr 31 [204-205] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 32 [206-207] [Type Int]: Lit: Int(4)
                                                 Stmt 33 [222-223]: Expr: Expr 34 [222-223] [Type Int]: Lit: Int(7)
-                                        Stmt 56 [0-0]: Expr: Expr 57 [0-0] [Type Unit]: Expr Block: Block 38 [110-157] [Type Unit]:
+                                        Stmt 56 [0-0]: Expr: Expr 57 [110-157] [Type Unit]: Expr Block: Block 38 [110-157] [Type Unit]:
                                             Stmt 39 [142-147]: Semi: Expr 40 [142-146] [Type Unit]: Call:
                                                 Expr 41 [142-143] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                     Expr 42 [142-143] [Type (Int => Unit is Adj)]: Var: Item 1
@@ -182,7 +221,7 @@ fn conjugate_invert_with_output() {
                                                 Expr 46 [124-125] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                     Expr 47 [124-125] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 48 [126-127] [Type Int]: Lit: Int(1)
-                                        Stmt 58 [0-0]: Expr: Expr 59 [0-0] [Type Int]: Var: Local 49
+                                        Stmt 58 [0-0]: Expr: Expr 59 [172-233] [Type Int]: Var: Local 49
                                 Stmt 35 [243-246]: Expr: Expr 36 [243-246] [Type Int]: Var: Local 13
                         adj: <none>
                         ctl: <none>
@@ -190,6 +229,90 @@ fn conjugate_invert_with_output() {
     );
 }
 
+#[test]
+fn conjugate_invert_with_output_consumed_by_outer_expr() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                function ComputeValue() : Int { 7 }
+                operation A() : Int {
+                    let y = within {
+                        B(1);
+                    }
+                    apply {
+                        ComputeValue()
+                    };
+                    y + 1
+                }
+            }
+        "},
+        &expect![[r#"
+            Package:
+                Item 0 [0-253] (Public):
+                    Namespace (Ident 36 [10-14] "Test"): Item 1, Item 2, Item 3
+                Item 1 [21-58] (Internal):
+                    Parent: 0
+                    Callable 0 [21-58] (operation):
+                        name: Ident 1 [31-32] "B"
+                        input: Pat 2 [33-40] [Type Int]: Bind: Ident 3 [33-34] "i"
+                        output: Unit
+                        functors: Adj
+                        body: SpecDecl 4 [21-58]: Impl:
+                            Block 5 [56-58]: <empty>
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>
+                Item 2 [63-98] (Internal):
+                    Parent: 0
+                    Callable 6 [63-98] (function):
+                        name: Ident 7 [72-84] "ComputeValue"
+                        input: Pat 8 [84-86] [Type Unit]: Unit
+                        output: Int
+                        functors: empty set
+                        body: SpecDecl 9 [63-98]: Impl:
+                            Block 10 [93-98] [Type Int]:
+                                Stmt 11 [95-96]: Expr: Expr 12 [95-96] [Type Int]: Lit: Int(7)
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>
+                Item 3 [103-251] (Internal):
+                    Parent: 0
+                    Callable 13 [103-251] (operation):
+                        name: Ident 14 [113-114] "A"
+                        input: Pat 15 [114-116] [Type Unit]: Unit
+                        output: Int
+                        functors: empty set
+                        body: SpecDecl 16 [103-251]: Impl:
+                            Block 17 [123-251] [Type Int]:
+                                Stmt 18 [133-231]: Local (Immutable):
+                                    Pat 19 [137-138] [Type Int]: Bind: Ident 20 [137-138] "y"
+                                    Expr 54 [141-230] [Type Int]: Expr Block: Block 47 [141-230] [Type Int]:
+                                        Stmt 48 [0-0]: Expr: Expr 49 [148-177] [Type Unit]: Expr Block: Block 22 [148-177] [Type Unit]:
+                                            Stmt 23 [162-167]: Semi: Expr 24 [162-166] [Type Unit]: Call:
+                                                Expr 25 [162-163] [Type (Int => Unit is Adj)]: Var: Item 1
+                                                Expr 26 [164-165] [Type Int]: Lit: Int(1)
+                                        Stmt 44 [0-0]: Local (Immutable):
+                                            Pat 45 [192-230] [Type Int]: Bind: Ident 43 [192-230] "@apply_res"
+                                            Expr 46 [192-230] [Type Int]: Expr Block: Block 27 [192-230] [Type Int]:
+                                                Stmt 28 [206-220]: Expr: Expr 29 [206-220] [Type Int]: Call:
+                                                    Expr 30 [206-218] [Type (Unit -> Int)]: Var: Item 2
+                                                    Expr 31 [218-220] [Type Unit]: Unit
+                                        Stmt 50 [0-0]: Expr: Expr 51 [148-177] [Type Unit]: Expr Block: Block 37 [148-177] [Type Unit]:
+                                            Stmt 38 [162-167]: Semi: Expr 39 [162-166] [Type Unit]: Call:
+                                                Expr 40 [162-163] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
+                                                    Expr 41 [162-163] [Type (Int => Unit is Adj)]: Var: Item 1
+                                                Expr 42 [164-165] [Type Int]: Lit: Int(1)
+                                        Stmt 52 [0-0]: Expr: Expr 53 [192-230] [Type Int]: Var: Local 43
+                                Stmt 32 [240-245]: Expr: Expr 33 [240-245] [Type Int]: BinOp (Add):
+                                    Expr 34 [240-241] [Type Int]: Var: Local 20
+                                    Expr 35 [244-245] [Type Int]: Lit: Int(1)
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>"#]],
+    );
+}
+
 #[test]
 fn nested_conjugate_invert() {
     check(
@@ -240,13 +363,13 @@ fn nested_conjugate_invert() {
                         functors: empty set
                         body: SpecDecl 9 [63-355]: Impl:
                             Block 10 [84-355] [Type Unit]:
-                                Stmt 11 [94-349]: Expr: Expr 87 [0-0] [Type Unit]: Expr Block: Block 80 [0-0] [Type Unit]:
-                                    Stmt 81 [0-0]: Expr: Expr 82 [0-0] [Type Unit]: Expr Block: Block 13 [101-287] [Type Unit]:
+                                Stmt 11 [94-349]: Expr: Expr 87 [94-349] [Type Unit]: Expr Block: Block 80 [94-349] [Type Unit]:
+                                    Stmt 81 [0-0]: Expr: Expr 82 [101-287] [Type Unit]: Expr Block: Block 13 [101-287] [Type Unit]:
                                         Stmt 14 [115-120]: Semi: Expr 15 [115-119] [Type Unit]: Call:
                                             Expr 16 [115-116] [Type (Int => Unit is Adj)]: Var: Item 1
                                             Expr 17 [117-118] [Type Int]: Lit: Int(0)
-                                        Stmt 18 [133-277]: Expr: Expr 110 [0-0] [Type Unit]: Expr Block: Block 103 [0-0] [Type Unit]:
-                                            Stmt 104 [0-0]: Expr: Expr 105 [0-0] [Type Unit]: Expr Block: Block 20 [140-199] [Type Unit]:
+                                        Stmt 18 [133-277]: Expr: Expr 110 [133-277] [Type Unit]: Expr Block: Block 103 [133-277] [Type Unit]:
+                                            Stmt 104 [0-0]: Expr: Expr 105 [140-199] [Type Unit]: Expr Block: Block 20 [140-199] [Type Unit]:
                                                 Stmt 21 [158-163]: Semi: Expr 22 [158-162] [Type Unit]: Call:
                                                     Expr 23 [158-159] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 24 [160-161] [Type Int]: Lit: Int(1)
@@ -254,15 +377,15 @@ fn nested_conjugate_invert() {
                                                     Expr 27 [180-181] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 28 [182-183] [Type Int]: Lit: Int(2)
                                             Stmt 100 [0-0]: Local (Immutable):
-                                                Pat 101 [0-0] [Type Unit]: Bind: Ident 99 [0-0] "@apply_res"
-                                                Expr 102 [0-0] [Type Unit]: Expr Block: Block 29 [218-277] [Type Unit]:
+                                                Pat 101 [218-277] [Type Unit]: Bind: Ident 99 [218-277] "@apply_res"
+                                                Expr 102 [218-277] [Type Unit]: Expr Block: Block 29 [218-277] [Type Unit]:
                                                     Stmt 30 [236-241]: Semi: Expr 31 [236-240] [Type Unit]: Call:
                                                         Expr 32 [236-237] [Type (Int => Unit is Adj)]: Var: Item 1
                                                         Expr 33 [238-239] [Type Int]: Lit: Int(3)
                                                     Stmt 34 [258-263]: Semi: Expr 35 [258-262] [Type Unit]: Call:
                                                         Expr 36 [258-259] [Type (Int => Unit is Adj)]: Var: Item 1
                                                         Expr 37 [260-261] [Type Int]: Lit: Int(4)
-                                            Stmt 106 [0-0]: Expr: Expr 107 [0-0] [Type Unit]: Expr Block: Block 88 [140-199] [Type Unit]:
+                                            Stmt 106 [0-0]: Expr: Expr 107 [140-199] [Type Unit]: Expr Block: Block 88 [140-199] [Type Unit]:
                                                 Stmt 89 [180-185]: Semi: Expr 90 [180-184] [Type Unit]: Call:
                                                     Expr 91 [180-181] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                         Expr 92 [180-181] [Type (Int => Unit is Adj)]: Var: Item 1
@@ -271,19 +394,19 @@ fn nested_conjugate_invert() {
                                                     Expr 96 [158-159] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                         Expr 97 [158-159] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 98 [160-161] [Type Int]: Lit: Int(1)
-                                            Stmt 108 [0-0]: Expr: Expr 109 [0-0] [Type Unit]: Var: Local 99
+                                            Stmt 108 [0-0]: Expr: Expr 109 [218-277] [Type Unit]: Var: Local 99
                                     Stmt 77 [0-0]: Local (Immutable):
-                                        Pat 78 [0-0] [Type Unit]: Bind: Ident 76 [0-0] "@apply_res"
-                                        Expr 79 [0-0] [Type Unit]: Expr Block: Block 38 [302-349] [Type Unit]:
+                                        Pat 78 [302-349] [Type Unit]: Bind: Ident 76 [302-349] "@apply_res"
+                                        Expr 79 [302-349] [Type Unit]: Expr Block: Block 38 [302-349] [Type Unit]:
                                             Stmt 39 [316-321]: Semi: Expr 40 [316-320] [Type Unit]: Call:
                                                 Expr 41 [316-317] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 42 [318-319] [Type Int]: Lit: Int(5)
                                             Stmt 43 [334-339]: Semi: Expr 44 [334-338] [Type Unit]: Call:
                                                 Expr 45 [334-335] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 46 [336-337] [Type Int]: Lit: Int(6)
-                                    Stmt 83 [0-0]: Expr: Expr 84 [0-0] [Type Unit]: Expr Block: Block 48 [101-287] [Type Unit]:
-                                        Stmt 49 [133-277]: Expr: Expr 133 [0-0] [Type Unit]: Expr Block: Block 126 [0-0] [Type Unit]:
-                                            Stmt 127 [0-0]: Expr: Expr 128 [0-0] [Type Unit]: Expr Block: Block 51 [140-199] [Type Unit]:
+                                    Stmt 83 [0-0]: Expr: Expr 84 [101-287] [Type Unit]: Expr Block: Block 48 [101-287] [Type Unit]:
+                                        Stmt 49 [133-277]: Expr: Expr 133 [133-277] [Type Unit]: Expr Block: Block 126 [133-277] [Type Unit]:
+                                            Stmt 127 [0-0]: Expr: Expr 128 [140-199] [Type Unit]: Expr Block: Block 51 [140-199] [Type Unit]:
                                                 Stmt 52 [158-163]: Semi: Expr 53 [158-162] [Type Unit]: Call:
                                                     Expr 54 [158-159] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 55 [160-161] [Type Int]: Lit: Int(1)
@@ -291,8 +414,8 @@ fn nested_conjugate_invert() {
                                                     Expr 58 [180-181] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 59 [182-183] [Type Int]: Lit: Int(2)
                                             Stmt 123 [0-0]: Local (Immutable):
-                                                Pat 124 [0-0] [Type Unit]: Bind: Ident 122 [0-0] "@apply_res"
-                                                Expr 125 [0-0] [Type Unit]: Expr Block: Block 60 [218-277] [Type Unit]:
+                                                Pat 124 [218-277] [Type Unit]: Bind: Ident 122 [218-277] "@apply_res"
+                                                Expr 125 [218-277] [Type Unit]: Expr Block: Block 60 [218-277] [Type Unit]:
                                                     Stmt 61 [258-263]: Semi: Expr 62 [258-262] [Type Unit]: Call:
                                                         Expr 63 [258-259] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                             Expr 64 [258-259] [Type (Int => Unit is Adj)]: Var: Item 1
@@ -301,7 +424,7 @@ fn nested_conjugate_invert() {
                                                         Expr 68 [236-237] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                             Expr 69 [236-237] [Type (Int => Unit is Adj)]: Var: Item 1
                                                         Expr 70 [238-239] [Type Int]: Lit: Int(3)
-                                            Stmt 129 [0-0]: Expr: Expr 130 [0-0] [Type Unit]: Expr Block: Block 111 [140-199] [Type Unit]:
+                                            Stmt 129 [0-0]: Expr: Expr 130 [140-199] [Type Unit]: Expr Block: Block 111 [140-199] [Type Unit]:
                                                 Stmt 112 [180-185]: Semi: Expr 113 [180-184] [Type Unit]: Call:
                                                     Expr 114 [180-181] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                         Expr 115 [180-181] [Type (Int => Unit is Adj)]: Var: Item 1
@@ -310,12 +433,12 @@ fn nested_conjugate_invert() {
                                                     Expr 119 [158-159] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                         Expr 120 [158-159] [Type (Int => Unit is Adj)]: Var: Item 1
                                                     Expr 121 [160-161] [Type Int]: Lit: Int(1)
-                                            Stmt 131 [0-0]: Expr: Expr 132 [0-0] [Type Unit]: Var: Local 122
+                                            Stmt 131 [0-0]: Expr: Expr 132 [218-277] [Type Unit]: Var: Local 122
                                         Stmt 71 [115-120]: Semi: Expr 72 [115-119] [Type Unit]: Call:
                                             Expr 73 [115-116] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                 Expr 74 [115-116] [Type (Int => Unit is Adj)]: Var: Item 1
                                             Expr 75 [117-118] [Type Int]: Lit: Int(0)
-                                    Stmt 85 [0-0]: Expr: Expr 86 [0-0] [Type Unit]: Var: Local 76
+                                    Stmt 85 [0-0]: Expr: Expr 86 [302-349] [Type Unit]: Var: Local 76
                         adj: <none>
                         ctl: <none>
                         ctl-adj: <none>"#]],
@@ -363,6 +486,35 @@ fn conjugate_invalid_op_fails() {
     );
 }
 
+#[test]
+fn conjugate_partial_application_without_adj_fails() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int, q : Qubit) : Unit is Ctl {}
+                operation A(ctls : Qubit[], q : Qubit) : Unit {
+                    within {
+                        (Controlled B(_, (1, q)))(ctls);
+                    }
+                    apply {}
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                AdjGen(
+                    MissingAdjFunctor(
+                        Span {
+                            lo: 152,
+                            hi: 175,
+                        },
+                    ),
+                ),
+            ]
+        "#]],
+    );
+}
+
 #[test]
 fn conjugate_not_separable_fail() {
     check(
@@ -398,6 +550,377 @@ fn conjugate_not_separable_fail() {
     );
 }
 
+#[test]
+fn conjugate_assign_in_within_fail_points_at_assignment() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    mutable a = 0;
+                    within {
+                        B(1);
+                        set a = 1;
+                        B(a);
+                    }
+                    apply {}
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                AdjGen(
+                    LogicSep(
+                        ExprForbidden(
+                            Span {
+                                lo: 156,
+                                hi: 165,
+                            },
+                        ),
+                    ),
+                ),
+            ]
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_repeat_until_in_within_fail() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    within {
+                        mutable i = 0;
+                        repeat {
+                            B(i);
+                        } until i > 2
+                        fixup {
+                            set i += 1;
+                        }
+                    }
+                    apply {}
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                AdjGen(
+                    LogicSep(
+                        ExprForbidden(
+                            Span {
+                                lo: 142,
+                                hi: 260,
+                            },
+                        ),
+                    ),
+                ),
+            ]
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_empty_within_reduces_to_apply_block() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    within {}
+                    apply {
+                        B(3);
+                        B(4);
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            Package:
+                Item 0 [0-173] (Public):
+                    Namespace (Ident 23 [10-14] "Test"): Item 1, Item 2
+                Item 1 [21-58] (Internal):
+                    Parent: 0
+                    Callable 0 [21-58] (operation):
+                        name: Ident 1 [31-32] "B"
+                        input: Pat 2 [33-40] [Type Int]: Bind: Ident 3 [33-34] "i"
+                        output: Unit
+                        functors: Adj
+                        body: SpecDecl 4 [21-58]: Impl:
+                            Block 5 [56-58]: <empty>
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>
+                Item 2 [63-171] (Internal):
+                    Parent: 0
+                    Callable 6 [63-171] (operation):
+                        name: Ident 7 [73-74] "A"
+                        input: Pat 8 [74-76] [Type Unit]: Unit
+                        output: Unit
+                        functors: empty set
+                        body: SpecDecl 9 [63-171]: Impl:
+                            Block 10 [84-171] [Type Unit]:
+                                Stmt 11 [94-165]: Expr: Expr 12 [94-165] [Type Unit]: Expr Block: Block 14 [118-165] [Type Unit]:
+                                    Stmt 15 [132-137]: Semi: Expr 16 [132-136] [Type Unit]: Call:
+                                        Expr 17 [132-133] [Type (Int => Unit is Adj)]: Var: Item 1
+                                        Expr 18 [134-135] [Type Int]: Lit: Int(3)
+                                    Stmt 19 [150-155]: Semi: Expr 20 [150-154] [Type Unit]: Call:
+                                        Expr 21 [150-151] [Type (Int => Unit is Adj)]: Var: Item 1
+                                        Expr 22 [152-153] [Type Int]: Lit: Int(4)
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>"#]],
+    );
+}
+
+#[derive(Default)]
+struct NodeIdCollector {
+    ids: Vec<NodeId>,
+}
+
+impl<'a> Visitor<'a> for NodeIdCollector {
+    fn visit_block(&mut self, block: &'a Block) {
+        self.ids.push(block.id);
+        visit::walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        self.ids.push(stmt.id);
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        self.ids.push(expr.id);
+        visit::walk_expr(self, expr);
+    }
+
+    fn visit_pat(&mut self, pat: &'a Pat) {
+        self.ids.push(pat.id);
+        visit::walk_pat(self, pat);
+    }
+}
+
+#[test]
+fn conjugate_invert_draws_new_node_ids_from_the_assigner() {
+    let store = PackageStore::new(compile::core());
+    let mut unit = compile_unit(
+        &store,
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    within {
+                        for i in 0..2 {
+                            B(i);
+                        }
+                        for x in [3, 4] {
+                            B(x);
+                        }
+                        within {
+                            B(5);
+                        }
+                        apply {
+                            B(6);
+                        }
+                    }
+                    apply {
+                        B(7);
+                    }
+                }
+            }
+        "},
+    );
+
+    let mut ids_before = NodeIdCollector::default();
+    ids_before.visit_package(&unit.package);
+    let max_id_before = ids_before
+        .ids
+        .iter()
+        .max()
+        .copied()
+        .expect("package should have nodes");
+
+    let errors = invert_conjugate_exprs(
+        store.core(),
+        &mut unit.package,
+        &mut unit.assigner,
+        InversionMode::Transform,
+        None,
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+
+    // Every node the pass adds, including the copies in the inverted within-blocks, gets an
+    // id that is fresh from the assigner rather than one reused from the original package.
+    let mut ids_after = NodeIdCollector::default();
+    ids_after.visit_package(&unit.package);
+    let new_ids: Vec<_> = ids_after
+        .ids
+        .iter()
+        .filter(|id| !ids_before.ids.contains(id))
+        .collect();
+    assert!(!new_ids.is_empty());
+    for id in new_ids {
+        assert!(
+            *id > max_id_before,
+            "{id} should be newer than {max_id_before}"
+        );
+    }
+}
+
+struct SpanCollector {
+    generated_exprs: Vec<Span>,
+    stmts: Vec<Span>,
+}
+
+impl<'a> Visitor<'a> for SpanCollector {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        self.stmts.push(stmt.span);
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        // The source below has no block expressions or local variables of its own, so these
+        // are all generated by the pass.
+        if matches!(
+            expr.kind,
+            ExprKind::Block(_) | ExprKind::Var(Res::Local(_), _)
+        ) {
+            self.generated_exprs.push(expr.span);
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+#[test]
+fn conjugate_generated_expr_spans_point_at_conjugate() {
+    let conjugate = "within { B(1); } apply { B(2); }";
+    let source = format!(
+        "namespace Test {{ operation B(i : Int) : Unit is Adj {{}} operation A() : Unit {{ {conjugate} }} }}"
+    );
+    let lo = u32::try_from(
+        source
+            .find(conjugate)
+            .expect("source should contain conjugate"),
+    )
+    .expect("offset should fit in u32");
+    let hi = lo + u32::try_from(conjugate.len()).expect("length should fit in u32");
+
+    let store = PackageStore::new(compile::core());
+    let mut unit = compile_unit(&store, &source);
+
+    let errors = invert_conjugate_exprs(
+        store.core(),
+        &mut unit.package,
+        &mut unit.assigner,
+        InversionMode::Transform,
+        None,
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let mut spans = SpanCollector {
+        generated_exprs: Vec::new(),
+        stmts: Vec::new(),
+    };
+    spans.visit_package(&unit.package);
+    // The wrapper block, the within-block, the apply binding, the adjoint within-block and
+    // the reference to the apply result.
+    assert_eq!(
+        5,
+        spans.generated_exprs.len(),
+        "{:?}",
+        spans.generated_exprs
+    );
+    for span in &spans.generated_exprs {
+        assert!(
+            lo <= span.lo && span.lo < span.hi && span.hi <= hi,
+            "{span:?} should be inside the conjugate at {lo}-{hi}"
+        );
+    }
+    // The generated statements keep default spans, so the debugger treats them as generated.
+    let default_stmts = spans
+        .stmts
+        .iter()
+        .filter(|span| **span == Span::default())
+        .count();
+    assert_eq!(4, default_stmts);
+}
+
+fn compile_standalone_block_test(body: &str) -> (PackageStore, CompileUnit) {
+    let store = PackageStore::new(compile::core());
+    let unit = compile_unit(
+        &store,
+        &format!(
+            "namespace Test {{ operation B(i : Int) : Unit is Adj {{}} operation A() : Unit {{ {body} }} }}"
+        ),
+    );
+    (store, unit)
+}
+
+fn body_of_a(package: &mut Package) -> &mut Block {
+    package
+        .items
+        .values_mut()
+        .find_map(|item| match &mut item.kind {
+            ItemKind::Callable(decl) if decl.name.name.as_ref() == "A" => {
+                match &mut decl.body.body {
+                    SpecBody::Impl(_, block) => Some(block),
+                    SpecBody::Gen(_) => None,
+                }
+            }
+            _ => None,
+        })
+        .expect("callable A should have a body")
+}
+
+#[test]
+fn invert_block_for_stmt_inverts_standalone_block() {
+    let (store, mut unit) = compile_standalone_block_test("B(1); B(2);");
+    let block = body_of_a(&mut unit.package);
+    invert_block_for_stmt(store.core(), &mut unit.assigner, block)
+        .expect("block should be inverted");
+
+    let args: Vec<_> = block
+        .stmts
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            StmtKind::Semi(expr) => match &expr.kind {
+                ExprKind::Call(callee, arg) => {
+                    assert!(matches!(
+                        callee.kind,
+                        ExprKind::UnOp(UnOp::Functor(Functor::Adj), _)
+                    ));
+                    arg.kind.clone()
+                }
+                _ => panic!("statement should be a call"),
+            },
+            _ => panic!("statement should be a call"),
+        })
+        .collect();
+    assert_eq!(
+        vec![ExprKind::Lit(Lit::Int(2)), ExprKind::Lit(Lit::Int(1))],
+        args
+    );
+    Validator::default().visit_package(&unit.package);
+}
+
+#[test]
+fn invert_block_for_stmt_fails_on_inseparable_block() {
+    let (store, mut unit) = compile_standalone_block_test("mutable a = 0; B(1); set a = 1; B(a);");
+    let block = body_of_a(&mut unit.package);
+    let original = block.to_string();
+    let errors = invert_block_for_stmt(store.core(), &mut unit.assigner, block)
+        .expect_err("block should not be inverted");
+    assert!(
+        matches!(
+            errors.as_slice(),
+            [crate::Error::ConjInvert(super::Error::AdjGen(_))]
+        ),
+        "{errors:?}"
+    );
+    assert_eq!(original, block.to_string());
+}
+
 #[test]
 fn conjugate_mutable_update_in_apply_fail() {
     check(
@@ -420,6 +943,7 @@ fn conjugate_mutable_update_in_apply_fail() {
         &expect![[r#"
             [
                 ApplyAssign(
+                    "a",
                     Span {
                         lo: 209,
                         hi: 210,
@@ -430,6 +954,131 @@ fn conjugate_mutable_update_in_apply_fail() {
     );
 }
 
+#[test]
+fn conjugate_mutable_tuple_update_in_apply_fail_names_variable() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    mutable a = 1;
+                    mutable theta = 2;
+                    within {
+                        B(theta);
+                    }
+                    apply {
+                        set (a, theta) = (3, 4);
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                ApplyAssign(
+                    "theta",
+                    Span {
+                        lo: 221,
+                        hi: 226,
+                    },
+                ),
+            ]
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_array_update_in_apply_fail() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    mutable arr = [1, 2];
+                    within {
+                        B(arr[0]);
+                    }
+                    apply {
+                        set arr w/= 0 <- 5;
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                ApplyAssign(
+                    "arr",
+                    Span {
+                        lo: 198,
+                        hi: 201,
+                    },
+                ),
+            ]
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_compound_assign_in_apply_fail() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    mutable a = 1;
+                    within {
+                        B(a);
+                    }
+                    apply {
+                        set a += 2;
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                ApplyAssign(
+                    "a",
+                    Span {
+                        lo: 186,
+                        hi: 187,
+                    },
+                ),
+            ]
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_element_assign_in_apply_fail() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    mutable arr = [1, 2];
+                    within {
+                        B(arr[0]);
+                    }
+                    apply {
+                        set arr[0] = 5;
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                ApplyAssign(
+                    "arr",
+                    Span {
+                        lo: 198,
+                        hi: 201,
+                    },
+                ),
+            ]
+        "#]],
+    );
+}
+
 #[test]
 fn conjugate_return_in_apply_fail() {
     check(
@@ -461,6 +1110,63 @@ fn conjugate_return_in_apply_fail() {
     );
 }
 
+#[test]
+fn conjugate_lambda_return_in_apply_succeeds() {
+    check_dry_run(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    within {
+                        B(1);
+                    }
+                    apply {
+                        let f = i -> {
+                            return i + 1;
+                        };
+                        B(f(2));
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            []
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_nested_return_in_apply_fail() {
+    check(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A(c : Bool) : Unit {
+                    within {
+                        B(1);
+                    }
+                    apply {
+                        if c {
+                            return ();
+                        }
+                        B(2);
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                ReturnForbidden(
+                    Span {
+                        lo: 190,
+                        hi: 199,
+                    },
+                ),
+            ]
+        "#]],
+    );
+}
+
 #[test]
 fn conjugate_mutable_correct_use_succeeds() {
     check(
@@ -512,8 +1218,8 @@ fn conjugate_mutable_correct_use_succeeds() {
                                 Stmt 11 [94-108]: Local (Mutable):
                                     Pat 12 [102-103] [Type Int]: Bind: Ident 13 [102-103] "a"
                                     Expr 14 [106-107] [Type Int]: Lit: Int(1)
-                                Stmt 15 [117-329]: Expr: Expr 82 [0-0] [Type Unit]: Expr Block: Block 75 [0-0] [Type Unit]:
-                                    Stmt 76 [0-0]: Expr: Expr 77 [0-0] [Type Unit]: Expr Block: Block 17 [124-217] [Type Unit]:
+                                Stmt 15 [117-329]: Expr: Expr 82 [117-329] [Type Unit]: Expr Block: Block 75 [117-329] [Type Unit]:
+                                    Stmt 76 [0-0]: Expr: Expr 77 [124-217] [Type Unit]: Expr Block: Block 17 [124-217] [Type Unit]:
                                         Stmt 18 [138-148]: Local (Immutable):
                                             Pat 19 [142-143] [Type Int]: Bind: Ident 20 [142-143] "x"
                                             Expr 21 [146-147] [Type Int]: Var: Local 13
@@ -527,8 +1233,8 @@ fn conjugate_mutable_correct_use_succeeds() {
                                             Pat 31 [201-202] [Type Int]: Bind: Ident 32 [201-202] "y"
                                             Expr 33 [205-206] [Type Int]: Var: Local 20
                                     Stmt 72 [0-0]: Local (Immutable):
-                                        Pat 73 [0-0] [Type Unit]: Bind: Ident 71 [0-0] "@apply_res"
-                                        Expr 74 [0-0] [Type Unit]: Expr Block: Block 34 [232-329] [Type Unit]:
+                                        Pat 73 [232-329] [Type Unit]: Bind: Ident 71 [232-329] "@apply_res"
+                                        Expr 74 [232-329] [Type Unit]: Expr Block: Block 34 [232-329] [Type Unit]:
                                             Stmt 35 [246-260]: Local (Mutable):
                                                 Pat 36 [254-255] [Type Int]: Bind: Ident 37 [254-255] "b"
                                                 Expr 38 [258-259] [Type Int]: Var: Local 13
@@ -541,7 +1247,7 @@ fn conjugate_mutable_correct_use_succeeds() {
                                             Stmt 47 [314-319]: Semi: Expr 48 [314-318] [Type Unit]: Call:
                                                 Expr 49 [314-315] [Type (Int => Unit is Adj)]: Var: Item 1
                                                 Expr 50 [316-317] [Type Int]: Lit: Int(4)
-                                    Stmt 78 [0-0]: Expr: Expr 79 [0-0] [Type Unit]: Expr Block: Block 52 [124-217] [Type Unit]:
+                                    Stmt 78 [0-0]: Expr: Expr 79 [124-217] [Type Unit]: Expr Block: Block 52 [124-217] [Type Unit]:
                                         Stmt 53 [138-148]: Local (Immutable):
                                             Pat 54 [142-143] [Type Int]: Bind: Ident 55 [142-143] "x"
                                             Expr 56 [146-147] [Type Int]: Var: Local 13
@@ -556,9 +1262,229 @@ fn conjugate_mutable_correct_use_succeeds() {
                                             Expr 68 [161-162] [Type (Int => Unit is Adj)]: UnOp (Functor Adj):
                                                 Expr 69 [161-162] [Type (Int => Unit is Adj)]: Var: Item 1
                                             Expr 70 [163-164] [Type Int]: Lit: Int(1)
-                                    Stmt 80 [0-0]: Expr: Expr 81 [0-0] [Type Unit]: Var: Local 71
+                                    Stmt 80 [0-0]: Expr: Expr 81 [232-329] [Type Unit]: Var: Local 71
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>"#]],
+    );
+}
+
+#[test]
+fn conjugate_within_and_apply_blocks_keep_their_own_bindings() {
+    // A binding in the within-block is scoped to that block, so the apply-block can't use
+    // it and has to bind its own `theta`. Both bindings must keep resolving to themselves
+    // after the within-block is duplicated and inverted around the apply-block.
+    check(
+        indoc! {"
+            namespace Test {
+                function F(x : Double) : Double { x }
+                operation Rz(theta : Double, q : Qubit) : Unit is Adj {}
+                operation A(x : Double, q : Qubit) : Unit {
+                    within {
+                        let theta = F(x);
+                        Rz(theta, q);
+                    }
+                    apply {
+                        let theta = F(x);
+                        Rz(theta, q);
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            Package:
+                Item 0 [0-340] (Public):
+                    Namespace (Ident 54 [10-14] "Test"): Item 1, Item 2, Item 3
+                Item 1 [21-58] (Internal):
+                    Parent: 0
+                    Callable 0 [21-58] (function):
+                        name: Ident 1 [30-31] "F"
+                        input: Pat 2 [32-42] [Type Double]: Bind: Ident 3 [32-33] "x"
+                        output: Double
+                        functors: empty set
+                        body: SpecDecl 4 [21-58]: Impl:
+                            Block 5 [53-58] [Type Double]:
+                                Stmt 6 [55-56]: Expr: Expr 7 [55-56] [Type Double]: Var: Local 3
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>
+                Item 2 [63-119] (Internal):
+                    Parent: 0
+                    Callable 8 [63-119] (operation):
+                        name: Ident 9 [73-75] "Rz"
+                        input: Pat 10 [75-102] [Type (Double, Qubit)]: Tuple:
+                            Pat 11 [76-90] [Type Double]: Bind: Ident 12 [76-81] "theta"
+                            Pat 13 [92-101] [Type Qubit]: Bind: Ident 14 [92-93] "q"
+                        output: Unit
+                        functors: Adj
+                        body: SpecDecl 15 [63-119]: Impl:
+                            Block 16 [117-119]: <empty>
+                        adj: <none>
+                        ctl: <none>
+                        ctl-adj: <none>
+                Item 3 [124-338] (Internal):
+                    Parent: 0
+                    Callable 17 [124-338] (operation):
+                        name: Ident 18 [134-135] "A"
+                        input: Pat 19 [135-158] [Type (Double, Qubit)]: Tuple:
+                            Pat 20 [136-146] [Type Double]: Bind: Ident 21 [136-137] "x"
+                            Pat 22 [148-157] [Type Qubit]: Bind: Ident 23 [148-149] "q"
+                        output: Unit
+                        functors: empty set
+                        body: SpecDecl 24 [124-338]: Impl:
+                            Block 25 [166-338] [Type Unit]:
+                                Stmt 26 [176-332]: Expr: Expr 80 [176-332] [Type Unit]: Expr Block: Block 73 [176-332] [Type Unit]:
+                                    Stmt 74 [0-0]: Expr: Expr 75 [183-250] [Type Unit]: Expr Block: Block 28 [183-250] [Type Unit]:
+                                        Stmt 29 [197-214]: Local (Immutable):
+                                            Pat 30 [201-206] [Type Double]: Bind: Ident 31 [201-206] "theta"
+                                            Expr 32 [209-213] [Type Double]: Call:
+                                                Expr 33 [209-210] [Type (Double -> Double)]: Var: Item 1
+                                                Expr 34 [211-212] [Type Double]: Var: Local 21
+                                        Stmt 35 [227-240]: Semi: Expr 36 [227-239] [Type Unit]: Call:
+                                            Expr 37 [227-229] [Type ((Double, Qubit) => Unit is Adj)]: Var: Item 2
+                                            Expr 38 [229-239] [Type (Double, Qubit)]: Tuple:
+                                                Expr 39 [230-235] [Type Double]: Var: Local 31
+                                                Expr 40 [237-238] [Type Qubit]: Var: Local 23
+                                    Stmt 70 [0-0]: Local (Immutable):
+                                        Pat 71 [265-332] [Type Unit]: Bind: Ident 69 [265-332] "@apply_res"
+                                        Expr 72 [265-332] [Type Unit]: Expr Block: Block 41 [265-332] [Type Unit]:
+                                            Stmt 42 [279-296]: Local (Immutable):
+                                                Pat 43 [283-288] [Type Double]: Bind: Ident 44 [283-288] "theta"
+                                                Expr 45 [291-295] [Type Double]: Call:
+                                                    Expr 46 [291-292] [Type (Double -> Double)]: Var: Item 1
+                                                    Expr 47 [293-294] [Type Double]: Var: Local 21
+                                            Stmt 48 [309-322]: Semi: Expr 49 [309-321] [Type Unit]: Call:
+                                                Expr 50 [309-311] [Type ((Double, Qubit) => Unit is Adj)]: Var: Item 2
+                                                Expr 51 [311-321] [Type (Double, Qubit)]: Tuple:
+                                                    Expr 52 [312-317] [Type Double]: Var: Local 44
+                                                    Expr 53 [319-320] [Type Qubit]: Var: Local 23
+                                    Stmt 76 [0-0]: Expr: Expr 77 [183-250] [Type Unit]: Expr Block: Block 55 [183-250] [Type Unit]:
+                                        Stmt 56 [197-214]: Local (Immutable):
+                                            Pat 57 [201-206] [Type Double]: Bind: Ident 58 [201-206] "theta"
+                                            Expr 59 [209-213] [Type Double]: Call:
+                                                Expr 60 [209-210] [Type (Double -> Double)]: Var: Item 1
+                                                Expr 61 [211-212] [Type Double]: Var: Local 21
+                                        Stmt 62 [227-240]: Semi: Expr 63 [227-239] [Type Unit]: Call:
+                                            Expr 64 [227-229] [Type ((Double, Qubit) => Unit is Adj)]: UnOp (Functor Adj):
+                                                Expr 65 [227-229] [Type ((Double, Qubit) => Unit is Adj)]: Var: Item 2
+                                            Expr 66 [229-239] [Type (Double, Qubit)]: Tuple:
+                                                Expr 67 [230-235] [Type Double]: Var: Local 58
+                                                Expr 68 [237-238] [Type Qubit]: Var: Local 23
+                                    Stmt 78 [0-0]: Expr: Expr 79 [265-332] [Type Unit]: Var: Local 69
                         adj: <none>
                         ctl: <none>
                         ctl-adj: <none>"#]],
     );
 }
+
+#[test]
+fn conjugate_dry_run_reports_errors_without_transforming() {
+    check_dry_run(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    mutable a = 1;
+                    within {
+                        let x = a;
+                        B(2);
+                    }
+                    apply {
+                        return ();
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            [
+                ReturnForbidden(
+                    Span {
+                        lo: 205,
+                        hi: 214,
+                    },
+                ),
+            ]
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_dry_run_leaves_valid_package_untouched() {
+    check_dry_run(
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit is Adj {}
+                operation A() : Unit {
+                    within {
+                        B(1);
+                    }
+                    apply {
+                        B(2);
+                    }
+                }
+            }
+        "},
+        &expect![[r#"
+            []
+        "#]],
+    );
+}
+
+#[test]
+fn conjugate_custom_resolver_chooses_adjoint() {
+    // Resolve the adjoint of `B` to `BAdj`, so that operations without an adjoint
+    // specialization can be used in within-blocks.
+    let store = PackageStore::new(compile::core());
+    let mut unit = compile_unit(
+        &store,
+        indoc! {"
+            namespace Test {
+                operation B(i : Int) : Unit {}
+                operation BAdj(i : Int) : Unit {}
+                operation A() : Unit {
+                    within {
+                        B(1);
+                        B(2);
+                    }
+                    apply {
+                        B(3);
+                    }
+                }
+            }
+        "},
+    );
+
+    let adj_id = unit
+        .package
+        .items
+        .values()
+        .find_map(|item| match &item.kind {
+            ItemKind::Callable(decl) if decl.name.name.as_ref() == "BAdj" => Some(ItemId {
+                package: None,
+                item: item.id,
+            }),
+            _ => None,
+        })
+        .expect("callable BAdj should exist");
+    let resolver = |op: &Expr| {
+        let mut adj_op = op.clone();
+        adj_op.kind = ExprKind::Var(Res::Item(adj_id), Vec::new());
+        Some(adj_op)
+    };
+    let errors = invert_conjugate_exprs_with_resolver(store.core(), &mut unit, &resolver);
+    assert!(errors.is_empty(), "{errors:?}");
+    Validator::default().visit_package(&unit.package);
+
+    // Both calls of the inverted within-block call the resolved adjoint.
+    let package = unit.package.to_string();
+    let adj_var = format!("Var: {adj_id}");
+    assert_eq!(
+        2,
+        package
+            .lines()
+            .filter(|line| line.trim_end().ends_with(&adj_var))
+            .count(),
+        "{package}"
+    );
+    assert!(!package.contains("Functor Adj"), "{package}");
+}
@@ -1,6 +1,8 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
+use std::mem::take;
+
 use qsc_data_structures::span::Span;
 use qsc_hir::{
     assigner::Assigner,
@@ -66,22 +68,26 @@ impl<'a> MutVisitor for BlockInverter<'a> {
     }
 
     fn visit_expr(&mut self, expr: &mut Expr) {
-        match &mut expr.kind {
-            ExprKind::For(pat, iterable, block) if self.should_reverse_loop => {
-                self.visit_block(block);
-                *expr = self.reverse_loop(pat, iterable, block);
+        match take(&mut expr.kind) {
+            ExprKind::For(pat, iterable, mut block) if self.should_reverse_loop => {
+                self.visit_block(&mut block);
+                *expr = self.reverse_loop(pat, *iterable, block);
             }
-            ExprKind::Conjugate(_, apply) => {
+            ExprKind::Conjugate(within, mut apply) => {
                 // Only invert the apply block, within block inversion handled by a different pass.
-                self.visit_block(apply);
+                self.visit_block(&mut apply);
+                expr.kind = ExprKind::Conjugate(within, apply);
+            }
+            kind => {
+                expr.kind = kind;
+                walk_expr(self, expr);
             }
-            _ => walk_expr(self, expr),
         }
     }
 }
 
 impl<'a> BlockInverter<'a> {
-    fn reverse_loop(&mut self, pat: &Pat, iterable: &Expr, block: &Block) -> Expr {
+    fn reverse_loop(&mut self, pat: Pat, iterable: Expr, block: Block) -> Expr {
         let mut wrapper = Block {
             id: NodeId::default(),
             span: Span::default(),
@@ -92,13 +98,8 @@ impl<'a> BlockInverter<'a> {
             Ty::Prim(Prim::Range) => self.reverse_range_loop(&mut wrapper, iterable, pat, block),
 
             Ty::Array(arr_ty) => {
-                self.reverse_array_loop(
-                    &mut wrapper,
-                    arr_ty,
-                    iterable.clone(),
-                    pat.clone(),
-                    block.clone(),
-                );
+                let arr_ty = arr_ty.as_ref().clone();
+                self.reverse_array_loop(&mut wrapper, &arr_ty, iterable, pat, block);
             }
 
             _ => panic!("iterable should be array or range"),
@@ -207,13 +208,7 @@ impl<'a> BlockInverter<'a> {
         });
     }
 
-    fn reverse_range_loop(
-        &mut self,
-        wrapper: &mut Block,
-        iterable: &Expr,
-        pat: &Pat,
-        block: &Block,
-    ) {
+    fn reverse_range_loop(&mut self, wrapper: &mut Block, iterable: Expr, pat: Pat, block: Block) {
         // Create a new binding for the range expr.
         let new_range_id = self.assigner.next_node();
         wrapper.stmts.push(Stmt {
@@ -231,7 +226,7 @@ impl<'a> BlockInverter<'a> {
                         name: generated_name("range"),
                     }),
                 },
-                iterable.clone(),
+                iterable,
             ),
         });
 
@@ -243,11 +238,7 @@ impl<'a> BlockInverter<'a> {
                 id: NodeId::default(),
                 span: Span::default(),
                 ty: Ty::UNIT,
-                kind: ExprKind::For(
-                    pat.clone(),
-                    Box::new(make_range_reverse_expr(new_range_id)),
-                    block.clone(),
-                ),
+                kind: ExprKind::For(pat, Box::new(make_range_reverse_expr(new_range_id)), block),
             }),
         });
     }
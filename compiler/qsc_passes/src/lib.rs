@@ -27,7 +27,7 @@ use qsc_frontend::compile::CompileUnit;
 use qsc_hir::{
     assigner::Assigner,
     global::{self, Table},
-    hir::Package,
+    hir::{Block, Package},
     mut_visit::MutVisitor,
     validate::Validator,
     visit::Visitor,
@@ -37,6 +37,8 @@ use qsc_rca::{PackageComputeProperties, PackageStoreComputeProperties};
 use replace_qubit_allocation::ReplaceQubitAllocation;
 use thiserror::Error;
 
+pub use spec_gen::adj_gen::AdjResolver;
+
 pub(crate) static CORE_NAMESPACE: &[&str] = &["Std", "Core"];
 pub(crate) static QIR_RUNTIME_NAMESPACE: &[&str] = &["QIR", "Runtime"];
 
@@ -106,7 +108,13 @@ impl PassContext {
         let spec_errors = spec_gen::generate_specs(core, package, assigner);
         Validator::default().visit_package(package);
 
-        let conjugate_errors = conjugate_invert::invert_conjugate_exprs(core, package, assigner);
+        let conjugate_errors = conjugate_invert::invert_conjugate_exprs(
+            core,
+            package,
+            assigner,
+            conjugate_invert::InversionMode::Transform,
+            None,
+        );
         Validator::default().visit_package(package);
 
         let measurement_decl_errors = measurement::validate_measurement_declarations(package);
@@ -151,6 +159,58 @@ pub fn run_default_passes(
     PassContext::new().run_default_passes(&mut unit.package, &mut unit.assigner, core, package_type)
 }
 
+/// Report the errors of the conjugate inversion pass without modifying the package, e.g. for
+/// pre-flight diagnostics. The full inversion runs on a clone of the package, so errors that
+/// only appear while inverting within-blocks are reported too.
+pub fn check_conjugate_inversion(core: &Table, unit: &mut CompileUnit) -> Vec<Error> {
+    conjugate_invert::invert_conjugate_exprs(
+        core,
+        &mut unit.package,
+        &mut unit.assigner,
+        conjugate_invert::InversionMode::DryRun,
+        None,
+    )
+    .into_iter()
+    .map(Error::ConjInvert)
+    .collect()
+}
+
+/// Eliminates the conjugate expressions of `unit` like the default passes do, but consults
+/// `resolver` to choose the adjoint of each operation called in the inverted within-blocks,
+/// e.g. to pair an operation with a hand-written inverse.
+pub fn invert_conjugate_exprs_with_resolver(
+    core: &Table,
+    unit: &mut CompileUnit,
+    resolver: &AdjResolver<'_>,
+) -> Vec<Error> {
+    conjugate_invert::invert_conjugate_exprs(
+        core,
+        &mut unit.package,
+        &mut unit.assigner,
+        conjugate_invert::InversionMode::Transform,
+        Some(resolver),
+    )
+    .into_iter()
+    .map(Error::ConjInvert)
+    .collect()
+}
+
+/// Replaces `block` with its adjoint inverse, as is done for the within-blocks of conjugate
+/// expressions, so that custom transformations can invert standalone blocks.
+///
+/// Fails if the classical and quantum statements of `block` can't be separated, in which case
+/// `block` is left untouched, or if the adjoint can't be distributed over its operation calls.
+pub fn invert_block_for_stmt(
+    core: &Table,
+    assigner: &mut Assigner,
+    block: &mut Block,
+) -> Result<(), Vec<Error>> {
+    match conjugate_invert::invert_block(core, assigner, block, None) {
+        Ok(errors) if errors.is_empty() => Ok(()),
+        Ok(errors) | Err(errors) => Err(errors.into_iter().map(Error::ConjInvert).collect()),
+    }
+}
+
 pub fn run_core_passes(core: &mut CompileUnit) -> Vec<Error> {
     let mut borrow_check = borrowck::Checker::default();
     borrow_check.visit_package(&core.package);
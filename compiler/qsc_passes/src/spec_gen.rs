@@ -204,7 +204,10 @@ impl<'a> SpecImplPass<'a> {
             );
             return;
         }
-        let mut distrib = AdjDistrib { errors: Vec::new() };
+        let mut distrib = AdjDistrib {
+            errors: Vec::new(),
+            resolver: None,
+        };
         distrib.visit_block(&mut adj_block);
         self.errors
             .extend(distrib.errors.into_iter().map(Error::AdjGen));
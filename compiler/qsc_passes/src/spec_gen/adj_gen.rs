@@ -23,16 +23,26 @@ pub enum Error {
     LogicSep(logic_sep::Error),
 }
 
-pub(crate) struct AdjDistrib {
+/// Chooses the adjoint of the callee of an operation call. Returning `None` falls back to
+/// applying the `Adjoint` functor to the callee.
+pub type AdjResolver<'a> = dyn Fn(&Expr) -> Option<Expr> + 'a;
+
+pub(crate) struct AdjDistrib<'a> {
     pub(crate) errors: Vec<Error>,
+    pub(crate) resolver: Option<&'a AdjResolver<'a>>,
 }
 
-impl MutVisitor for AdjDistrib {
+impl MutVisitor for AdjDistrib<'_> {
     fn visit_expr(&mut self, expr: &mut Expr) {
         match &mut expr.kind {
             ExprKind::Call(op, _) => {
                 match &op.ty {
                     Ty::Arrow(arrow) if arrow.kind == CallableKind::Operation => {
+                        if let Some(adj_op) = self.resolver.and_then(|resolver| resolver(&**op)) {
+                            *op = Box::new(adj_op);
+                            walk_expr(self, expr);
+                            return;
+                        }
                         let functors = arrow
                             .functors
                             .expect_value("arrow type should have concrete functors");
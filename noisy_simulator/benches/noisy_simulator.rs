@@ -1,6 +1,8 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
+#[cfg(feature = "rayon")]
+use criterion::BenchmarkId;
 use criterion::{criterion_group, criterion_main, Criterion};
 use nalgebra::dmatrix;
 use noisy_simulator::{
@@ -84,5 +86,49 @@ pub fn state_vector_simulator(c: &mut Criterion) {
     );
 }
 
+#[cfg(feature = "rayon")]
+fn sixteen_qubits_hadamard_layer(chunk_size: usize) -> Result<(), Error> {
+    let h_gate = Operation::new(vec![dmatrix![
+        std::f64::consts::FRAC_1_SQRT_2.into(), std::f64::consts::FRAC_1_SQRT_2.into();
+        std::f64::consts::FRAC_1_SQRT_2.into(), (-std::f64::consts::FRAC_1_SQRT_2).into();
+    ]])?;
+
+    let mut sim = StateVectorSimulator::new_with_seed(16, 42);
+    sim.set_parallel_chunk_size(chunk_size);
+    for qubit in 0..16 {
+        sim.apply_operation(&h_gate, &[qubit])?;
+    }
+
+    Ok(())
+}
+
+/// Chunking only matters when the kernel runs in parallel, so this group needs the `rayon`
+/// feature. A 16-qubit state vector has 65536 entries, well above the kernel's parallel
+/// threshold of 4096 entries.
+#[cfg(feature = "rayon")]
+pub fn parallel_chunk_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel_chunk_size");
+    for chunk_size in [1 << 6, 1 << 8, 1 << 10, 1 << 12, 1 << 14, 1 << 16] {
+        group.bench_with_input(
+            BenchmarkId::new("16 qubits hadamard layer", chunk_size),
+            &chunk_size,
+            |b, &chunk_size| {
+                b.iter(|| {
+                    sixteen_qubits_hadamard_layer(chunk_size).expect("bench should succeed");
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+#[cfg(feature = "rayon")]
+criterion_group!(
+    benches,
+    density_matrix_simulator,
+    state_vector_simulator,
+    parallel_chunk_size
+);
+#[cfg(not(feature = "rayon"))]
 criterion_group!(benches, density_matrix_simulator, state_vector_simulator);
 criterion_main!(benches);
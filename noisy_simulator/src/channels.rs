@@ -0,0 +1,87 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! This module contains constructors for common single-qubit noise channels.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{operation::Operation, Error, SquareMatrix};
+use nalgebra::dmatrix;
+use num_complex::Complex;
+
+/// Returns the depolarizing channel ρ ↦ (1 - p) ρ + p I / 2, with Kraus operators
+/// √(1 - 3p/4) I, √(p/4) X, √(p/4) Y, and √(p/4) Z.
+pub fn depolarizing(p: f64) -> Result<Operation, Error> {
+    check_parameter("p", p)?;
+    let (identity_factor, pauli_factor) = ((1.0 - 0.75 * p).sqrt(), (0.25 * p).sqrt());
+    Operation::new(vec![
+        identity() * Complex::from(identity_factor),
+        pauli_x() * Complex::from(pauli_factor),
+        pauli_y() * Complex::from(pauli_factor),
+        pauli_z() * Complex::from(pauli_factor),
+    ])
+}
+
+/// Returns the amplitude damping channel, which decays |1⟩ to |0⟩ with probability `gamma`.
+/// Its Kraus operators are |0⟩⟨0| + √(1 - γ) |1⟩⟨1| and √γ |0⟩⟨1|.
+pub fn amplitude_damping(gamma: f64) -> Result<Operation, Error> {
+    check_parameter("gamma", gamma)?;
+    Operation::new(vec![
+        real_matrix([[1.0, 0.0], [0.0, (1.0 - gamma).sqrt()]]),
+        real_matrix([[0.0, gamma.sqrt()], [0.0, 0.0]]),
+    ])
+}
+
+/// Returns the phase damping channel, which shrinks the coherences of the state by a
+/// factor √(1 - λ) without changing the populations. Its Kraus operators are
+/// |0⟩⟨0| + √(1 - λ) |1⟩⟨1| and √λ |1⟩⟨1|.
+pub fn phase_damping(lambda: f64) -> Result<Operation, Error> {
+    check_parameter("lambda", lambda)?;
+    Operation::new(vec![
+        real_matrix([[1.0, 0.0], [0.0, (1.0 - lambda).sqrt()]]),
+        real_matrix([[0.0, 0.0], [0.0, lambda.sqrt()]]),
+    ])
+}
+
+/// Returns the bit flip channel, which applies an X gate with probability `p`.
+/// Its Kraus operators are √(1 - p) I and √p X.
+pub fn bit_flip(p: f64) -> Result<Operation, Error> {
+    check_parameter("p", p)?;
+    Operation::new(vec![
+        identity() * Complex::from((1.0 - p).sqrt()),
+        pauli_x() * Complex::from(p.sqrt()),
+    ])
+}
+
+/// Checks that the channel parameter `name` is a probability in [0, 1].
+fn check_parameter(name: &str, value: f64) -> Result<(), Error> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::FailedToConstructOperation(format!(
+            "channel parameter `{name}` should be in [0, 1], but it is {value}"
+        )))
+    }
+}
+
+/// Returns the 2 x 2 matrix with the given real `rows`.
+fn real_matrix(rows: [[f64; 2]; 2]) -> SquareMatrix {
+    dmatrix![rows[0][0], rows[0][1]; rows[1][0], rows[1][1]].map(Complex::from)
+}
+
+fn identity() -> SquareMatrix {
+    real_matrix([[1.0, 0.0], [0.0, 1.0]])
+}
+
+fn pauli_x() -> SquareMatrix {
+    real_matrix([[0.0, 1.0], [1.0, 0.0]])
+}
+
+fn pauli_y() -> SquareMatrix {
+    dmatrix![Complex::ZERO, -Complex::I; Complex::I, Complex::ZERO]
+}
+
+fn pauli_z() -> SquareMatrix {
+    real_matrix([[1.0, 0.0], [0.0, -1.0]])
+}
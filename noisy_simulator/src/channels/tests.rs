@@ -0,0 +1,65 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+use super::{amplitude_damping, bit_flip, depolarizing, phase_damping};
+use crate::{
+    tests::{assert_approx_eq, noiseless_tests::noiseless_h},
+    DensityMatrixSimulator, Error, NoisySimulator,
+};
+
+#[test]
+fn check_channels_are_trace_preserving() {
+    for parameter in [0.0, 0.1, 0.5, 1.0] {
+        for channel in [depolarizing, amplitude_damping, phase_damping, bit_flip] {
+            let operation = channel(parameter).expect("channel should be valid");
+            assert_eq!(1, operation.number_of_qubits());
+            assert_eq!(Ok(()), operation.validate_completeness());
+        }
+    }
+}
+
+#[test]
+fn check_full_depolarizing_yields_maximally_mixed_state() {
+    let h = noiseless_h();
+    let depolarizing = depolarizing(1.0).expect("channel should be valid");
+    let mut sim = DensityMatrixSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&depolarizing, &[0])
+        .expect("operation should succeed");
+
+    let state = sim.state().expect("state should be valid").data();
+    for (l, r) in [0.5, 0., 0., 0.5].iter().zip(state.iter()) {
+        assert_approx_eq(*l, r.re);
+        assert_approx_eq(0., r.im);
+    }
+}
+
+#[test]
+fn check_full_amplitude_damping_decays_to_ground_state() {
+    let h = noiseless_h();
+    let amplitude_damping = amplitude_damping(1.0).expect("channel should be valid");
+    let mut sim = DensityMatrixSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&amplitude_damping, &[0])
+        .expect("operation should succeed");
+
+    let state = sim.state().expect("state should be valid").data();
+    for (l, r) in [1., 0., 0., 0.].iter().zip(state.iter()) {
+        assert_approx_eq(*l, r.re);
+        assert_approx_eq(0., r.im);
+    }
+}
+
+#[test]
+fn check_channel_parameters_out_of_range_fail() {
+    for parameter in [-0.1, 1.1, f64::NAN] {
+        for channel in [depolarizing, amplitude_damping, phase_damping, bit_flip] {
+            assert!(matches!(
+                channel(parameter),
+                Err(Error::FailedToConstructOperation(_))
+            ));
+        }
+    }
+}
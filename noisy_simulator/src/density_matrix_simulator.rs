@@ -8,7 +8,12 @@
 mod tests;
 
 use crate::{
-    handle_error, instrument::Instrument, kernel::apply_kernel, operation::Operation,
+    handle_error,
+    instrument::Instrument,
+    kernel::{apply_kernel, default_chunk_size},
+    linalg::von_neumann_entropy,
+    operation::Operation,
+    state_vector_simulator::{StateVector, StateVectorSimulator},
     ComplexVector, Error, NoisySimulator, SquareMatrix, TOLERANCE,
 };
 use num_complex::Complex;
@@ -93,6 +98,96 @@ impl DensityMatrix {
         self.number_of_qubits
     }
 
+    /// Returns the density matrix as a `dimension` x `dimension` matrix. `data` stores the
+    /// same entries in row-major order, i.e. the entry at row i and column j of the matrix is
+    /// the entry `dimension` * i + j of `data`.
+    #[must_use]
+    pub fn density_matrix(&self) -> SquareMatrix {
+        SquareMatrix::from_row_slice(self.dimension, self.dimension, self.data.as_slice())
+    }
+
+    /// Returns the probability ⟨i|ρ|i⟩ of measuring the computational basis state |i⟩,
+    /// where i is `basis_index` and qubit k corresponds to the k-th bit of i.
+    pub fn basis_state_probability(&self, basis_index: usize) -> Result<f64, Error> {
+        if basis_index >= self.dimension {
+            return Err(Error::BasisIndexOutOfBounds(basis_index, self.dimension));
+        }
+        Ok(self.data[(self.dimension + 1) * basis_index].re)
+    }
+
+    /// Returns the diagonal of the density matrix, i.e. the probabilities of measuring each
+    /// computational basis state, indexed as in `basis_state_probability`.
+    #[must_use]
+    pub fn populations(&self) -> Vec<f64> {
+        (0..self.dimension)
+            .map(|idx| self.data[(self.dimension + 1) * idx].re)
+            .collect()
+    }
+
+    /// Returns the purity Tr(ρ²) of the density matrix ρ. Pure states have purity 1, and
+    /// the maximally mixed state has purity 1 / `dimension`.
+    #[must_use]
+    pub fn purity(&self) -> f64 {
+        // Since ρ is Hermitian, Tr(ρ²) = Σᵢⱼ ρᵢⱼ ρⱼᵢ = Σᵢⱼ |ρᵢⱼ|².
+        self.data.norm_squared()
+    }
+
+    /// Returns the von Neumann entropy -Tr(ρ log₂ ρ) of the density matrix ρ, measured in
+    /// bits. Pure states have entropy 0, and the maximally mixed state has entropy
+    /// `number_of_qubits`. Eigenvalues smaller than `TOLERANCE` are treated as 0, following
+    /// the convention 0 log 0 = 0.
+    #[must_use]
+    pub fn von_neumann_entropy(&self) -> f64 {
+        von_neumann_entropy(&self.density_matrix())
+    }
+
+    /// Returns the Hilbert-Schmidt distance Tr((ρ - σ)²) between this density matrix ρ and
+    /// `other` density matrix σ. Unlike the trace distance, it doesn't require an
+    /// eigendecomposition.
+    pub fn hilbert_schmidt_distance(&self, other: &DensityMatrix) -> Result<f64, Error> {
+        if self.dimension != other.dimension {
+            return Err(Error::InvalidState(format!(
+                "density matrices should have the same dimensions, {} != {}",
+                self.dimension, other.dimension
+            )));
+        }
+        // Since ρ - σ is Hermitian, Tr((ρ - σ)²) = Σᵢⱼ |ρᵢⱼ - σᵢⱼ|².
+        Ok((&self.data - &other.data).norm_squared())
+    }
+
+    /// Returns the state vector |ψ⟩ such that this density matrix is |ψ⟩⟨ψ|, up to a global
+    /// phase, with the same `trace_change`.
+    ///
+    /// Errors: returns `Error::InvalidState` if the density matrix is not pure, i.e. if its
+    /// purity differs from 1 by more than `TOLERANCE`.
+    fn to_state_vector(&self) -> Result<StateVector, Error> {
+        let purity = self.purity();
+        if (purity - 1.0).abs() > TOLERANCE {
+            return Err(Error::InvalidState(format!(
+                "density matrix should be pure to convert it into a state vector, but its purity is {purity}"
+            )));
+        }
+        // Since ρ = |ψ⟩⟨ψ|, each column j of ρ is ψ scaled by ψⱼ*. We take the column with
+        // the largest diagonal entry |ψⱼ|² to minimize numerical errors.
+        let populations = self.populations();
+        let (col, population) = populations
+            .iter()
+            .enumerate()
+            .max_by(|(_, l), (_, r)| l.total_cmp(r))
+            .ok_or(Error::InvalidState("density matrix is empty".to_string()))?;
+        let norm = population.sqrt();
+        let data = ComplexVector::from_iterator(
+            self.dimension,
+            (0..self.dimension).map(|row| self.data[self.dimension * row + col] / norm),
+        );
+        StateVector::try_from(
+            self.dimension,
+            self.number_of_qubits,
+            self.trace_change,
+            data,
+        )
+    }
+
     /// Returns `true` if the matrix is Hermitian.
     fn is_hermitian(&self) -> bool {
         for row in 0..self.dimension {
@@ -152,11 +247,48 @@ impl DensityMatrix {
         Ok(())
     }
 
+    /// Removes a classical `qubit` from the density matrix and returns its value.
+    /// Returns `Error::QubitNotClassical` if the qubit isn't in a computational basis state.
+    fn factor_out_classical_qubit(&mut self, qubit: usize) -> Result<bool, Error> {
+        let mask = 1 << qubit;
+        let one_probability: f64 = (0..self.dimension)
+            .filter(|idx| idx & mask != 0)
+            .map(|idx| self.data[(self.dimension + 1) * idx].re)
+            .sum();
+        let value = if one_probability <= TOLERANCE {
+            false
+        } else if one_probability >= 1.0 - TOLERANCE {
+            true
+        } else {
+            return Err(Error::QubitNotClassical(qubit));
+        };
+
+        // Since the density matrix is positive semidefinite, a vanishing population of
+        // the other basis state implies that all its coherences vanish too. Therefore,
+        // ρ = |value⟩⟨value| ⊗ ρ', and we only need to keep the entries of ρ'.
+        let bit = if value { mask } else { 0 };
+        let expand_index = |idx: usize| ((idx >> qubit) << (qubit + 1)) | bit | (idx & (mask - 1));
+        let dimension = self.dimension >> 1;
+        let mut data = ComplexVector::zeros(dimension * dimension);
+        for row in 0..dimension {
+            for col in 0..dimension {
+                data[dimension * row + col] =
+                    self.data[self.dimension * expand_index(row) + expand_index(col)];
+            }
+        }
+
+        self.dimension = dimension;
+        self.number_of_qubits -= 1;
+        self.data = data;
+        Ok(value)
+    }
+
     /// Applies the operation matrix to the target qubits.
     fn apply_operation_matrix(
         &mut self,
         operation_matrix: &SquareMatrix,
         qubits: &[usize],
+        chunk_size: usize,
     ) -> Result<(), Error> {
         // TODO [Research]: Figure out why they do this qubits_expanded thing.
         let mut qubits_expanded = Vec::with_capacity(2 * qubits.len());
@@ -166,7 +298,12 @@ impl DensityMatrix {
         for id in qubits {
             qubits_expanded.push(*id + self.number_of_qubits());
         }
-        apply_kernel(&mut self.data, operation_matrix, &qubits_expanded)
+        apply_kernel(
+            &mut self.data,
+            operation_matrix,
+            &qubits_expanded,
+            chunk_size,
+        )
     }
 }
 
@@ -182,17 +319,112 @@ pub struct DensityMatrixSimulator {
     dimension: usize,
     /// Random number generator used for probabilistic operations.
     rng: StdRng,
+    /// If `trace_change` drops below this threshold the simulation is aborted.
+    min_trace_change: f64,
+    /// Number of state entries processed per chunk when applying kernels.
+    parallel_chunk_size: usize,
 }
 
 impl DensityMatrixSimulator {
-    fn check_out_of_bounds_qubits(&self, qubits: &[usize]) -> Result<(), Error> {
+    /// Checks that the `qubits` ids are in range and distinct.
+    fn check_qubits(&self, qubits: &[usize]) -> Result<(), Error> {
         let number_of_qubits = self.state.as_ref()?.number_of_qubits;
-        if let Some(id) = qubits.iter().find(|id| **id >= number_of_qubits) {
-            Err(Error::QubitIdOutOfBounds(*id))
-        } else {
-            Ok(())
+        for (i, id) in qubits.iter().enumerate() {
+            if *id >= number_of_qubits {
+                return Err(Error::QubitIndexOutOfRange {
+                    index: *id,
+                    number_of_qubits,
+                });
+            }
+            if qubits[..i].contains(id) {
+                return Err(Error::DuplicateQubit(*id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a new `DensityMatrixSimulator` in the given `state`, used to convert between
+    /// simulator backends.
+    pub(crate) fn from_state(state: DensityMatrix, rng: StdRng, min_trace_change: f64) -> Self {
+        let dimension = state.dimension();
+        Self {
+            state: Ok(state),
+            dimension,
+            rng,
+            min_trace_change,
+            parallel_chunk_size: default_chunk_size(dimension * dimension),
         }
     }
+
+    /// Converts this simulator into a `StateVectorSimulator` in the state |ψ⟩, where the
+    /// current state is |ψ⟩⟨ψ|. The number of qubits, `trace_change`, the random number
+    /// generator, and the minimum trace change are preserved.
+    ///
+    /// Errors: returns `Error::InvalidState` if the current state is not pure.
+    pub fn into_state_vector_simulator(self) -> Result<StateVectorSimulator, Error> {
+        let state_vector = self.state?.to_state_vector()?;
+        Ok(StateVectorSimulator::from_state(
+            state_vector,
+            self.rng,
+            self.min_trace_change,
+        ))
+    }
+
+    /// Marks a measured `qubit` as classical and factors it out of the density matrix,
+    /// halving its dimension. Returns the classical value of the qubit.
+    ///
+    /// Factoring is only valid if the qubit is in a computational basis state, which
+    /// implies it is unentangled from the rest of the system, e.g. right after it was
+    /// measured in the Z basis. Otherwise `Error::QubitNotClassical` is returned and the
+    /// state is left untouched.
+    ///
+    /// The qubits with ids greater than `qubit` are renumbered, their ids decrease by one.
+    pub fn factor_out_classical_qubit(&mut self, qubit: usize) -> Result<bool, Error> {
+        self.check_qubits(&[qubit])?;
+        let value = self.state.as_mut()?.factor_out_classical_qubit(qubit)?;
+        self.dimension >>= 1;
+        Ok(value)
+    }
+
+    /// Resets the quantum system to the ground state and its `trace_change` to 1.0, also
+    /// recovering the simulator if it was in an error state.
+    ///
+    /// Unlike constructing a new simulator, this preserves the configuration of the
+    /// simulator: its random number generator, which keeps advancing across trajectories,
+    /// the minimum trace change, and the parallel chunk size.
+    pub fn soft_reset(&mut self) {
+        self.state = Ok(DensityMatrix::new(self.dimension.ilog2() as usize));
+    }
+
+    /// Set a lower bound for the theoretical change in trace. If an operation, instrument,
+    /// or measurement pushes `trace_change` below this threshold, the simulation is aborted
+    /// and the simulator transitions into the `Error::TraceBelowThreshold` state.
+    ///
+    /// This is useful to cheaply discard low-weight trajectories in post-selection workflows.
+    /// The default threshold is 0.0, which never aborts a simulation.
+    pub fn set_min_trace_change(&mut self, threshold: f64) {
+        self.min_trace_change = threshold;
+    }
+
+    /// Set the number of density matrix entries the kernel processes per chunk when applying
+    /// operations and instruments. A `chunk_size` of 0 is treated as 1.
+    ///
+    /// The default is derived from the size of the density matrix and the number of available cores.
+    /// Too-small chunks spend more time on scheduling than on useful work, while too-large
+    /// chunks leave cores idle because there are fewer chunks than threads.
+    pub fn set_parallel_chunk_size(&mut self, chunk_size: usize) {
+        self.parallel_chunk_size = chunk_size.max(1);
+    }
+
+    /// Aborts the simulation if `trace_change` dropped below `min_trace_change`.
+    fn check_min_trace_change(&mut self) -> Result<(), Error> {
+        let trace_change = self.state.as_ref()?.trace_change;
+        if trace_change < self.min_trace_change {
+            let err = Error::TraceBelowThreshold(trace_change);
+            handle_error!(self, err);
+        }
+        Ok(())
+    }
 }
 
 impl NoisySimulator for DensityMatrixSimulator {
@@ -206,6 +438,8 @@ impl NoisySimulator for DensityMatrixSimulator {
             state: Ok(density_matrix),
             dimension,
             rng: StdRng::from_entropy(),
+            min_trace_change: 0.0,
+            parallel_chunk_size: default_chunk_size(dimension * dimension),
         }
     }
 
@@ -217,33 +451,39 @@ impl NoisySimulator for DensityMatrixSimulator {
             state: Ok(density_matrix),
             dimension,
             rng: StdRng::seed_from_u64(seed),
+            min_trace_change: 0.0,
+            parallel_chunk_size: default_chunk_size(dimension * dimension),
         }
     }
 
     /// Apply an operation to the given qubit ids.
     fn apply_operation(&mut self, operation: &Operation, qubits: &[usize]) -> Result<(), Error> {
-        self.check_out_of_bounds_qubits(qubits)?;
+        self.check_qubits(qubits)?;
 
-        self.state
-            .as_mut()?
-            .apply_operation_matrix(operation.matrix(), qubits)?;
+        self.state.as_mut()?.apply_operation_matrix(
+            operation.matrix(),
+            qubits,
+            self.parallel_chunk_size,
+        )?;
         if let Err(err) = self.state.as_mut()?.renormalize() {
             handle_error!(self, err);
         }
-        Ok(())
+        self.check_min_trace_change()
     }
 
     /// Apply non selective evolution to the given qubit ids.
     fn apply_instrument(&mut self, instrument: &Instrument, qubits: &[usize]) -> Result<(), Error> {
-        self.check_out_of_bounds_qubits(qubits)?;
+        self.check_qubits(qubits)?;
 
-        self.state
-            .as_mut()?
-            .apply_operation_matrix(instrument.non_selective_operation_matrix(), qubits)?;
+        self.state.as_mut()?.apply_operation_matrix(
+            instrument.non_selective_operation_matrix(),
+            qubits,
+            self.parallel_chunk_size,
+        )?;
         if let Err(err) = self.state.as_mut()?.renormalize() {
             handle_error!(self, err);
         }
-        Ok(())
+        self.check_min_trace_change()
     }
 
     /// Performs selective evolution under the given instrument.
@@ -269,13 +509,14 @@ impl NoisySimulator for DensityMatrixSimulator {
         qubits: &[usize],
         random_sample: f64,
     ) -> Result<usize, Error> {
-        self.check_out_of_bounds_qubits(qubits)?;
+        self.check_qubits(qubits)?;
 
         let mut tmp_state = self.state.clone()?;
         apply_kernel(
             &mut tmp_state.data,
             instrument.total_effect_transposed(),
             qubits,
+            self.parallel_chunk_size,
         )?;
         let total_effect_trace = tmp_state.trace()?;
         if total_effect_trace < TOLERANCE {
@@ -295,6 +536,7 @@ impl NoisySimulator for DensityMatrixSimulator {
                 &mut tmp_state.data,
                 instrument.operation(outcome).effect_matrix_transpose(),
                 qubits,
+                self.parallel_chunk_size,
             )?;
             let outcome_trace = tmp_state.trace()?;
             summed_probability += outcome_trace / total_effect_trace;
@@ -312,6 +554,7 @@ impl NoisySimulator for DensityMatrixSimulator {
         if let Err(err) = self.state.as_mut()?.apply_operation_matrix(
             instrument.operation(last_non_zero_trace_outcome).matrix(),
             qubits,
+            self.parallel_chunk_size,
         ) {
             handle_error!(self, err);
         };
@@ -322,6 +565,7 @@ impl NoisySimulator for DensityMatrixSimulator {
         {
             handle_error!(self, err);
         };
+        self.check_min_trace_change()?;
         Ok(last_non_zero_trace_outcome)
     }
 
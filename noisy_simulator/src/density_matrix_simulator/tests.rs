@@ -2,7 +2,17 @@
 // Licensed under the MIT License.
 
 use super::DensityMatrixSimulator;
-use crate::tests::{noiseless_tests, noisy_tests};
+use crate::{
+    operation::{operation, Operation},
+    tests::{
+        assert_approx_eq,
+        noiseless_tests::{self, noiseless_cnot, noiseless_h, noiseless_mz},
+        noisy_tests,
+    },
+    Error, NoisySimulator,
+};
+use num_complex::Complex;
+use std::f64::consts::FRAC_1_SQRT_2;
 
 #[test]
 fn check_measuring_plus_state_yields_zero_with_50_percent_probability() {
@@ -80,9 +90,216 @@ fn check_alternating_mz_and_mx_yield_right_probabilities() {
     );
 }
 
+#[test]
+fn check_trace_change_below_threshold_aborts_simulation() {
+    noiseless_tests::check_trace_change_below_threshold_aborts_simulation::<DensityMatrixSimulator>(
+    );
+}
+
+#[test]
+fn check_apply_operation_mask_targets_qubits_in_ascending_order() {
+    noiseless_tests::check_apply_operation_mask_targets_qubits_in_ascending_order::<
+        DensityMatrixSimulator,
+    >();
+}
+
+#[test]
+fn check_apply_operation_mask_rejects_invalid_masks() {
+    noiseless_tests::check_apply_operation_mask_rejects_invalid_masks::<DensityMatrixSimulator>();
+}
+
+#[test]
+fn check_apply_operation_rejects_invalid_qubit_indices() {
+    noiseless_tests::check_apply_operation_rejects_invalid_qubit_indices::<DensityMatrixSimulator>(
+    );
+}
+
+#[test]
+fn check_soft_reset_restores_ground_state_and_keeps_configuration() {
+    noiseless_tests::check_soft_reset_restores_ground_state_and_keeps_configuration::<
+        DensityMatrixSimulator,
+    >();
+}
+
 #[test]
 fn check_noisy_identity_yields_same_qubit_with_right_probability() {
     noisy_tests::check_noisy_identity_yields_same_qubit_with_right_probability::<
         DensityMatrixSimulator,
     >();
 }
+
+#[test]
+fn check_factor_out_classical_qubit_after_measurement() {
+    let (h, cnot, mz) = (noiseless_h(), noiseless_cnot(), noiseless_mz());
+    let mut sim = DensityMatrixSimulator::new(2);
+
+    // Make a Bell Pair. Entangled qubits are not classical.
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    assert_eq!(
+        sim.factor_out_classical_qubit(0),
+        Err(Error::QubitNotClassical(0))
+    );
+
+    // After measuring qubit 0 both qubits are classical and have the same value.
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], 0.7)
+        .expect("measurement should succeed");
+    assert_eq!(outcome, 1);
+    assert_eq!(sim.factor_out_classical_qubit(0), Ok(true));
+    let state = sim.state().expect("state should be valid");
+    assert_eq!(state.number_of_qubits(), 1);
+    assert_eq!(state.dimension(), 2);
+
+    // Qubit 1 was renumbered to qubit 0.
+    assert_eq!(sim.factor_out_classical_qubit(0), Ok(true));
+    assert_eq!(
+        sim.state()
+            .expect("state should be valid")
+            .number_of_qubits(),
+        0
+    );
+}
+
+#[test]
+fn check_purity_and_entropy_of_pure_state() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = DensityMatrixSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+    assert_approx_eq(1., state.purity());
+    assert_approx_eq(0., state.von_neumann_entropy());
+}
+
+#[test]
+fn check_purity_and_entropy_of_maximally_mixed_state() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = DensityMatrixSimulator::new(2);
+    for qubit in 0..2 {
+        sim.apply_operation(&h, &[qubit])
+            .expect("operation should succeed");
+        sim.apply_instrument(&mz, &[qubit])
+            .expect("instrument should succeed");
+    }
+    let state = sim.state().expect("state should be valid");
+    assert_approx_eq(0.25, state.purity());
+    assert_approx_eq(2., state.von_neumann_entropy());
+}
+
+#[test]
+fn check_hilbert_schmidt_distance_between_pure_and_mixed_states() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut pure_sim = DensityMatrixSimulator::new(1);
+    pure_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let mut mixed_sim = DensityMatrixSimulator::new(1);
+    mixed_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    mixed_sim
+        .apply_instrument(&mz, &[0])
+        .expect("instrument should succeed");
+
+    // |+⟩⟨+| - I/2 has entries ±0.5 off the diagonal only.
+    let pure = pure_sim.state().expect("state should be valid");
+    let mixed = mixed_sim.state().expect("state should be valid");
+    assert_approx_eq(
+        0.5,
+        pure.hilbert_schmidt_distance(mixed)
+            .expect("distance should be computed"),
+    );
+
+    let two_qubits = DensityMatrixSimulator::new(2);
+    assert!(matches!(
+        pure.hilbert_schmidt_distance(two_qubits.state().expect("state should be valid")),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn check_basis_state_probabilities_of_bell_pair() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = DensityMatrixSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+
+    for (l, r) in [0.5, 0., 0., 0.5].iter().zip(state.populations()) {
+        assert_approx_eq(*l, r);
+    }
+    assert_approx_eq(
+        0.5,
+        state
+            .basis_state_probability(0b11)
+            .expect("basis index should be valid"),
+    );
+    assert_eq!(
+        state.basis_state_probability(4),
+        Err(Error::BasisIndexOutOfBounds(4, 4))
+    );
+}
+
+#[test]
+fn check_into_state_vector_simulator_preserves_pure_state() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = DensityMatrixSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    sim.set_trace(0.25).expect("trace should be set");
+
+    let sv_sim = sim
+        .into_state_vector_simulator()
+        .expect("conversion should succeed");
+    let state = sv_sim.state().expect("state should be valid");
+    assert_eq!(2, state.number_of_qubits());
+    assert_approx_eq(0.25, state.trace_change());
+    let expected = [FRAC_1_SQRT_2, 0., 0., FRAC_1_SQRT_2];
+    for (l, r) in expected.iter().zip(state.data().iter()) {
+        assert_approx_eq(*l, r.re);
+        assert_approx_eq(0., r.im);
+    }
+}
+
+#[test]
+fn check_into_state_vector_simulator_fails_on_mixed_state() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = DensityMatrixSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_instrument(&mz, &[0])
+        .expect("instrument should succeed");
+    assert!(matches!(
+        sim.into_state_vector_simulator(),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn check_density_matrix_is_row_major() {
+    let h = noiseless_h();
+    let s = operation!([1., 0.;
+                        0., Complex::I;])
+    .expect("operation should be valid");
+    let mut sim = DensityMatrixSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&s, &[0])
+        .expect("operation should succeed");
+
+    // |+i⟩⟨+i| = ½ [1, -i; i, 1].
+    let density_matrix = sim.state().expect("state should be valid").density_matrix();
+    assert_eq!((2, 2), density_matrix.shape());
+    assert_approx_eq(-0.5, density_matrix[(0, 1)].im);
+    assert_approx_eq(0.5, density_matrix[(1, 0)].im);
+    assert_approx_eq(0.5, density_matrix[(1, 1)].re);
+}
@@ -0,0 +1,75 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! This module contains functions to compare probability distributions,
+//! such as the outcome distributions obtained from two different simulations.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{Error, TOLERANCE};
+
+/// Computes the Kullback-Leibler divergence D(p ‖ q) = Σᵢ pᵢ ln(pᵢ / qᵢ), measured in nats.
+///
+/// By convention, terms where pᵢ = 0 contribute nothing to the sum, and a term where
+/// pᵢ > 0 but qᵢ = 0 makes the divergence infinite.
+///
+/// Returns `Error::InvalidDistribution` if `p` and `q` have different lengths or if
+/// either of them is not normalized.
+pub fn kl_divergence(p: &[f64], q: &[f64]) -> Result<f64, Error> {
+    validate_distributions(p, q)?;
+    let mut divergence = 0.0;
+    for (p_i, q_i) in p.iter().zip(q) {
+        if *p_i < TOLERANCE {
+            continue;
+        }
+        if *q_i < TOLERANCE {
+            return Ok(f64::INFINITY);
+        }
+        divergence += p_i * (p_i / q_i).ln();
+    }
+    Ok(divergence)
+}
+
+/// Computes the total variation distance δ(p, q) = ½ Σᵢ |pᵢ - qᵢ|, which is the largest
+/// difference between the probabilities that `p` and `q` assign to the same event.
+///
+/// Returns `Error::InvalidDistribution` if `p` and `q` have different lengths or if
+/// either of them is not normalized.
+pub fn total_variation_distance(p: &[f64], q: &[f64]) -> Result<f64, Error> {
+    validate_distributions(p, q)?;
+    Ok(0.5
+        * p.iter()
+            .zip(q)
+            .map(|(p_i, q_i)| (p_i - q_i).abs())
+            .sum::<f64>())
+}
+
+/// Checks that `p` and `q` are valid distributions over the same number of outcomes.
+fn validate_distributions(p: &[f64], q: &[f64]) -> Result<(), Error> {
+    if p.len() != q.len() {
+        return Err(Error::InvalidDistribution(format!(
+            "distributions should have the same number of outcomes, {} != {}",
+            p.len(),
+            q.len()
+        )));
+    }
+    validate_distribution(p)?;
+    validate_distribution(q)
+}
+
+/// Checks that all probabilities are non-negative and that they sum to 1.
+fn validate_distribution(distribution: &[f64]) -> Result<(), Error> {
+    if let Some(p) = distribution.iter().find(|p| **p < -TOLERANCE) {
+        return Err(Error::InvalidDistribution(format!(
+            "probabilities should be non-negative, but found {p}"
+        )));
+    }
+    let total: f64 = distribution.iter().sum();
+    if (total - 1.0).abs() > TOLERANCE {
+        return Err(Error::InvalidDistribution(format!(
+            "probabilities should sum to 1, but they sum to {total}"
+        )));
+    }
+    Ok(())
+}
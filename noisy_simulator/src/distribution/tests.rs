@@ -0,0 +1,82 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+use super::{kl_divergence, total_variation_distance};
+use crate::{tests::assert_approx_eq, Error};
+
+#[test]
+fn check_kl_divergence_of_identical_distributions_is_zero() {
+    let p = [0.25, 0.25, 0.5];
+    let divergence = kl_divergence(&p, &p).expect("distributions should be valid");
+    assert_approx_eq(0.0, divergence);
+}
+
+#[test]
+fn check_kl_divergence_is_computed_correctly() {
+    let p = [0.5, 0.5];
+    let q = [0.25, 0.75];
+    let divergence = kl_divergence(&p, &q).expect("distributions should be valid");
+    assert_approx_eq(0.5 * 2.0_f64.ln() + 0.5 * (2.0_f64 / 3.0).ln(), divergence);
+}
+
+#[test]
+fn check_kl_divergence_ignores_zero_probability_terms() {
+    let p = [1.0, 0.0];
+    let q = [0.5, 0.5];
+    let divergence = kl_divergence(&p, &q).expect("distributions should be valid");
+    assert_approx_eq(2.0_f64.ln(), divergence);
+}
+
+#[test]
+fn check_kl_divergence_is_infinite_when_q_misses_support_of_p() {
+    let p = [0.5, 0.5];
+    let q = [1.0, 0.0];
+    let divergence = kl_divergence(&p, &q).expect("distributions should be valid");
+    assert!(divergence.is_infinite());
+}
+
+#[test]
+fn check_kl_divergence_fails_on_length_mismatch() {
+    let p = [0.5, 0.5];
+    let q = [0.25, 0.25, 0.5];
+    assert!(matches!(
+        kl_divergence(&p, &q),
+        Err(Error::InvalidDistribution(_))
+    ));
+}
+
+#[test]
+fn check_kl_divergence_fails_on_unnormalized_distribution() {
+    let p = [0.5, 0.5];
+    let q = [0.5, 0.25];
+    assert!(matches!(
+        kl_divergence(&p, &q),
+        Err(Error::InvalidDistribution(_))
+    ));
+}
+
+#[test]
+fn check_total_variation_distance_is_computed_correctly() {
+    let p = [0.5, 0.5, 0.0];
+    let q = [0.25, 0.25, 0.5];
+    let distance = total_variation_distance(&p, &q).expect("distributions should be valid");
+    assert_approx_eq(0.5, distance);
+}
+
+#[test]
+fn check_total_variation_distance_of_disjoint_distributions_is_one() {
+    let p = [1.0, 0.0];
+    let q = [0.0, 1.0];
+    let distance = total_variation_distance(&p, &q).expect("distributions should be valid");
+    assert_approx_eq(1.0, distance);
+}
+
+#[test]
+fn check_total_variation_distance_fails_on_length_mismatch() {
+    let p = [0.5, 0.5];
+    let q = [0.25, 0.25, 0.5];
+    assert!(matches!(
+        total_variation_distance(&p, &q),
+        Err(Error::InvalidDistribution(_))
+    ));
+}
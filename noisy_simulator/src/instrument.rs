@@ -8,7 +8,6 @@
 mod tests;
 
 use crate::{operation::Operation, Error, SquareMatrix, TOLERANCE};
-use nalgebra::{DMatrix, DVector};
 
 /// An instrument is the means by which we make measurements on a quantum system.
 pub struct Instrument {
@@ -78,6 +77,22 @@ impl Instrument {
         &self.summed_kraus_operators
     }
 
+    /// Return the Kraus operators of all the operations in this instrument, concatenated in
+    /// the order of the outcomes. Together they form a Kraus representation of the non
+    /// selective evolution, which lets the whole instrument be treated as a single channel.
+    ///
+    /// `non_selective_kraus_operators` represents the same channel with a minimal set of
+    /// Kraus operators derived from its Choi matrix, while this method preserves the Kraus
+    /// operators of each outcome. Like all the Kraus operators in the simulator, the returned
+    /// matrices are transposed. See `Operation::new` for more details.
+    #[must_use]
+    pub fn total_kraus_operators(&self) -> Vec<SquareMatrix> {
+        self.operations
+            .iter()
+            .flat_map(|operation| operation.kraus_operators().iter().cloned())
+            .collect()
+    }
+
     /// Return total effect Σᵢ Σₖ (Kᵢₖ† Kᵢₖ), where † denotes the adjoint.
     #[must_use]
     pub fn total_effect(&self) -> &SquareMatrix {
@@ -136,27 +151,5 @@ fn summed_kraus_operators(operations: &[Operation]) -> Result<Vec<SquareMatrix>,
 }
 
 fn compute_choi_matrix(operations: &[Operation]) -> SquareMatrix {
-    operations
-        .iter()
-        .map(|op| {
-            op.kraus_operators()
-                .iter()
-                .map(|k| {
-                    let vectorized_k = vectorize(k);
-                    &vectorized_k * &vectorized_k.adjoint()
-                })
-                .sum::<SquareMatrix>()
-        })
-        .sum()
-}
-
-/// Stacks the columns of `matrix` into a single column vector.
-///
-/// Performance note: Typically vectorization stacks the
-/// rows of a matrix into a single column vector. But since we
-/// transposed all matrices until now, we stack the columns instead.
-///
-/// See `noisy_simulator/src/operation.rs/Operation::new` for more details.
-fn vectorize<T: nalgebra::Scalar + Copy>(matrix: &DMatrix<T>) -> DVector<T> {
-    DVector::<T>::from_iterator(matrix.len(), matrix.iter().copied())
+    operations.iter().map(Operation::choi_matrix).sum()
 }
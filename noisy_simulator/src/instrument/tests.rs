@@ -3,7 +3,6 @@
 
 use super::Instrument;
 use crate::{
-    instrument::vectorize,
     operation::{operation, Operation},
     tests::assert_approx_eq,
     SquareMatrix,
@@ -14,13 +13,6 @@ use rand::{rngs::StdRng, Rng, SeedableRng};
 /// Seed for the random number generators.
 const SEED: u64 = 42;
 
-#[test]
-fn check_vectorize() {
-    let m = nalgebra::dmatrix![1, 2, 3;
-                               4, 5, 6];
-    assert_eq!(nalgebra::dvector![1, 4, 2, 5, 3, 6], vectorize(&m));
-}
-
 #[test]
 #[should_panic(expected = "instrument should be invalid")]
 fn check_ill_formed_instrument_throws_error() {
@@ -93,3 +85,31 @@ fn check_non_selective_evolution_operator_is_computed_correctly() {
         assert_approx_eq(x0.im, x1.im);
     }
 }
+
+#[test]
+fn check_total_kraus_operators_represent_non_selective_evolution() {
+    let op0 = operation!([1., 0.;
+                          0., 0.;])
+    .expect("operation should be valid");
+    let op1 = operation!([0., 0.;
+                          0., 0.6;],
+                         [0., 0.8;
+                          0., 0.;])
+    .expect("operation should be valid");
+    let instrument = Instrument::new(vec![op0, op1]).expect("instrument should be valid");
+
+    let total_kraus_operators = instrument.total_kraus_operators();
+    assert_eq!(3, total_kraus_operators.len());
+
+    // Performance note: (A ⊗ B)^T = A^T ⊗ B^T, so the transposed Kraus operators
+    // yield the operation matrix in the same form as `Operation::matrix`.
+    let operation_matrix: SquareMatrix = total_kraus_operators
+        .iter()
+        .map(|k| k.kronecker(&k.conjugate()))
+        .sum();
+    let expected = instrument.non_selective_operation_matrix();
+    for (x0, x1) in operation_matrix.iter().zip(expected.iter()) {
+        assert_approx_eq(x0.re, x1.re);
+        assert_approx_eq(x0.im, x1.im);
+    }
+}
@@ -6,6 +6,19 @@
 
 use crate::{ComplexVector, Error, SquareMatrix};
 use nalgebra::Complex;
+use std::{num::NonZeroUsize, ops::Range};
+
+/// Smallest chunk size returned by `default_chunk_size`. Below this size the cost of
+/// scheduling a chunk outweighs the work done in it.
+const MIN_CHUNK_SIZE: usize = 1 << 10;
+
+/// Returns the default number of state entries processed per chunk by `apply_kernel`
+/// for a state with `state_len` entries: a few chunks per available core, but never
+/// fewer than `MIN_CHUNK_SIZE` entries per chunk.
+pub(crate) fn default_chunk_size(state_len: usize) -> usize {
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    (state_len / (4 * threads)).max(MIN_CHUNK_SIZE)
+}
 
 /// This function extracts the relevant entries from the `state_vector` into its own vector.
 /// Then it applies the `operation_matrix` to this extracted entries.
@@ -19,10 +32,17 @@ use nalgebra::Complex;
 ///
 /// Errors: If the `operation_matrix` doesn't have the right dimension for the number of target `qubits`,
 /// this function will return `Error::MatrixVecDimensionMismatch`.
+///
+/// The state is processed in chunks of `chunk_size` entries. The chunks are the units of
+/// work handed to each thread when the kernel runs in parallel, which happens when the
+/// `rayon` feature is enabled and the state has at least `PARALLEL_THRESHOLD` entries.
+/// Each entry is computed by the same operations in both cases, so the parallel and serial
+/// results are identical.
 pub fn apply_kernel(
     state: &mut ComplexVector,
     operation_matrix: &SquareMatrix,
     qubits: &[usize],
+    chunk_size: usize,
 ) -> Result<(), Error> {
     // Construct a mask that has 1s at locations given by the target `qubits` ids.
     let mask = make_mask(state, qubits);
@@ -56,23 +76,106 @@ pub fn apply_kernel(
     }
 
     // Main loop.
+    let chunk_size = chunk_size.max(1);
+    let number_of_chunks = state.len().div_ceil(chunk_size);
+
+    #[cfg(feature = "rayon")]
+    if state.len() >= PARALLEL_THRESHOLD {
+        use rayon::prelude::*;
+        let state = StatePtr(state.as_mut_ptr(), state.len());
+        (0..number_of_chunks).into_par_iter().for_each_init(
+            || {
+                (
+                    ComplexVector::zeros(num_elements),
+                    ComplexVector::zeros(num_elements),
+                )
+            },
+            |(extracted_entries, new_entries), chunk| {
+                // SAFETY: `state` is exclusively borrowed for the whole parallel loop, and the
+                // chunks are disjoint ranges of base indices, so no two threads access the
+                // same entries. See `apply_chunk`.
+                unsafe {
+                    apply_chunk(
+                        &state,
+                        chunk * chunk_size..((chunk + 1) * chunk_size).min(state.1),
+                        mask,
+                        &index_offsets,
+                        operation_matrix,
+                        extracted_entries,
+                        new_entries,
+                    );
+                }
+            },
+        );
+        return Ok(());
+    }
+
     let mut extracted_entries = ComplexVector::zeros(num_elements);
     let mut new_entries = ComplexVector::zeros(num_elements);
-    for s in 0..state.len() {
+    let state = StatePtr(state.as_mut_ptr(), state.len());
+    for chunk in 0..number_of_chunks {
+        // SAFETY: `state` is exclusively borrowed for the whole loop. See `apply_chunk`.
+        unsafe {
+            apply_chunk(
+                &state,
+                chunk * chunk_size..((chunk + 1) * chunk_size).min(state.1),
+                mask,
+                &index_offsets,
+                operation_matrix,
+                &mut extracted_entries,
+                &mut new_entries,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of state entries below which `apply_kernel` stays serial even when the `rayon`
+/// feature is enabled, since for small states the threadpool overhead outweighs the work.
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD: usize = 1 << 12;
+
+/// A raw pointer to the entries of a state and its length, shared by the threads of
+/// `apply_kernel`.
+struct StatePtr(*mut Complex<f64>, usize);
+
+// SAFETY: the threads of `apply_kernel` access disjoint entries of the state.
+unsafe impl Send for StatePtr {}
+unsafe impl Sync for StatePtr {}
+
+/// Applies the `operation_matrix` to the entries `s | index_offsets[k]` of the state, for each
+/// base index `s` in `bases` with no bits in common with `mask`. Since each index of the state
+/// has a single base index, different `bases` ranges touch disjoint entries, and the result
+/// doesn't depend on the order in which the ranges are processed.
+///
+/// # Safety
+/// `state` must point to `state.1` valid entries that no other thread is accessing in the
+/// `bases` range, and `bases`, `mask` and `index_offsets` must be computed as in `apply_kernel`.
+unsafe fn apply_chunk(
+    state: &StatePtr,
+    bases: Range<usize>,
+    mask: usize,
+    index_offsets: &[usize],
+    operation_matrix: &SquareMatrix,
+    extracted_entries: &mut ComplexVector,
+    new_entries: &mut ComplexVector,
+) {
+    for s in bases {
         if (s & mask) == 0 {
             // Extract relevant entries into a vector to make the gate application easier.
             (0..index_offsets.len()).for_each(|k| {
                 // SAFETY: extracted_entries has size index_offset.len(), so that get_unchecked is safe.
-                // state.get_unchecked(idx) is safe because:
+                // state.0.add(idx) is safe because:
                 //  1. s has total_qubits_in_system bits
                 //  2. index_offset has total_qubits_in_system bits
                 //  3. Therefore, idx = s | index_offset also has total_qubits_in_system bits.
-                //  4. idx < (1 << total_qubits_in_system) = state.len() because
+                //  4. idx < (1 << total_qubits_in_system) = state.1 because
                 //     it has (total_qubits_in_system + 1) bits.
-                //  5. Therefore state.get_unchecked(idx) is safe.
+                //  5. Therefore state.0.add(idx) is safe.
                 let idx = s | index_offsets[k];
                 unsafe {
-                    *extracted_entries.get_unchecked_mut(k) = *state.get_unchecked(idx);
+                    *extracted_entries.get_unchecked_mut(k) = *state.0.add(idx);
                 }
             });
 
@@ -80,29 +183,21 @@ pub fn apply_kernel(
             new_entries.gemv_tr(
                 Complex::ONE,
                 operation_matrix,
-                &extracted_entries,
+                &*extracted_entries,
                 Complex::ZERO,
             );
 
             // Store accumulated result back into the state vector.
             (0..index_offsets.len()).for_each(|k| {
                 // SAFETY: new_entries has size index_offset.len(), so that get_unchecked is safe.
-                // state.get_unchecked(idx) is safe because:
-                //  1. s has total_qubits_in_system bits
-                //  2. index_offset has total_qubits_in_system bits
-                //  3. Therefore, idx = s | index_offset also has total_qubits_in_system bits.
-                //  4. idx < (1 << total_qubits_in_system) = state.len() because
-                //     it has (total_qubits_in_system + 1) bits.
-                //  5. Therefore state.get_unchecked(idx) is safe.
+                // state.0.add(idx) is safe for the same reasons as above.
                 let idx = s | index_offsets[k];
                 unsafe {
-                    *state.get_unchecked_mut(idx) = *new_entries.get_unchecked(k);
+                    *state.0.add(idx) = *new_entries.get_unchecked(k);
                 }
             });
         }
     }
-
-    Ok(())
 }
 
 /// Construct a mask that has 1s at locations given by the target `qubits` ids.
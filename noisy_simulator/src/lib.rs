@@ -29,13 +29,24 @@
 //!
 //! However if you are interested in a single or very few shots, you should use the state
 //! vector simulator.
+//!
+//! # Features
+//! - `rayon`: parallelizes the application of operations and instruments across the
+//!   available cores for systems large enough to amortize the threadpool overhead.
+//! - `serde`: implements `Serialize` and `Deserialize` for `StateVector`, so that
+//!   simulations can be checkpointed to disk and resumed.
 
 #![deny(missing_docs)]
 
+pub(crate) mod channels;
 pub(crate) mod density_matrix_simulator;
+pub(crate) mod distribution;
 pub(crate) mod instrument;
 pub(crate) mod kernel;
+pub(crate) mod linalg;
 pub(crate) mod operation;
+#[cfg(feature = "serde")]
+pub(crate) mod serialization;
 pub(crate) mod state_vector_simulator;
 #[cfg(test)]
 pub(crate) mod tests;
@@ -46,10 +57,12 @@ use thiserror::Error;
 
 // Re-exports.
 pub use {
+    channels::{amplitude_damping, bit_flip, depolarizing, phase_damping},
     density_matrix_simulator::{DensityMatrix, DensityMatrixSimulator},
+    distribution::{kl_divergence, total_variation_distance},
     instrument::Instrument,
     operation::Operation,
-    state_vector_simulator::{StateVector, StateVectorSimulator},
+    state_vector_simulator::{ShotStats, StateVector, StateVectorSimulator, TraceEvent},
 };
 
 /// A square matrix of `Complex<f64>`.
@@ -74,6 +87,28 @@ pub trait NoisySimulator {
     /// Apply an operation to the given qubit ids.
     fn apply_operation(&mut self, operation: &Operation, qubits: &[usize]) -> Result<(), Error>;
 
+    /// Apply an operation to the qubits given by the set bits of `qubit_mask`, in ascending
+    /// order. This avoids building a `Vec<usize>` of qubit ids in hot paths.
+    fn apply_operation_mask(
+        &mut self,
+        operation: &Operation,
+        qubit_mask: u64,
+    ) -> Result<(), Error> {
+        let mut qubits = [0; u64::BITS as usize];
+        let mut mask_qubits = 0;
+        for id in (0..u64::BITS as usize).filter(|id| (qubit_mask >> id) & 1 == 1) {
+            qubits[mask_qubits] = id;
+            mask_qubits += 1;
+        }
+        if mask_qubits != operation.number_of_qubits() {
+            return Err(Error::QubitMaskMismatch {
+                mask_qubits,
+                operation_qubits: operation.number_of_qubits(),
+            });
+        }
+        self.apply_operation(operation, &qubits[..mask_qubits])
+    }
+
     /// Apply non selective evolution to the given qubit ids.
     fn apply_instrument(&mut self, instrument: &Instrument, qubits: &[usize]) -> Result<(), Error>;
 
@@ -107,6 +142,8 @@ pub trait NoisySimulator {
     /// Return theoretical change in trace due to operations that have been applied so far
     /// In reality, the density matrix is always renormalized after instruments/operations
     /// have been applied.
+    ///
+    /// Over long circuits with many low-probability operations it can underflow to 0.
     fn trace_change(&self) -> Result<f64, Error>;
 
     /// Set the trace of the quantum system.
@@ -116,9 +153,15 @@ pub trait NoisySimulator {
 /// A noisy simulation error.
 #[derive(Clone, Debug, Error, PartialEq)]
 pub enum Error {
+    /// A basis state index is greater than the dimension of the system.
+    #[error("basis state index {0} out of bounds for a system of dimension {1}")]
+    BasisIndexOutOfBounds(usize, usize),
     /// Failure when building a `DensityMatrix` from raw data.
     #[error("error when building `DensityMatrix` from raw_data: {0}")]
     DensityMatrixTryFromError(String),
+    /// The same qubit id appears more than once where distinct qubits are expected.
+    #[error("qubit id {0} should not appear more than once")]
+    DuplicateQubit(usize),
     /// Failure when buidling an instrument.
     #[error("error when building instrument: {0}")]
     FailedToConstructInstrument(String),
@@ -131,6 +174,18 @@ pub enum Error {
     /// Failure when sampling Kraus operators.
     #[error("numerical error: no outcome found when sampling Kraus operators")]
     FailedToSampleKrausOperators,
+    /// An instrument outcome index is greater than the number of outcomes of the instrument.
+    #[error("instrument outcome index out of bounds: {0}")]
+    InstrumentOutcomeOutOfBounds(usize),
+    /// Provided an invalid probability distribution.
+    #[error("invalid probability distribution: {0}")]
+    InvalidDistribution(String),
+    /// Provided an invalid orthogonal projector.
+    #[error("invalid projector: {0}")]
+    InvalidProjector(String),
+    /// Provided an invalid order for a Rényi entropy.
+    #[error("Rényi entropy order should be a finite non-negative number, but it is {0}")]
+    InvalidRenyiOrder(f64),
     /// Provided an invalid state when creating or setting the state of the simulator.
     #[error("provided an invalid state when creating or setting the state of the simulator: {0}")]
     InvalidState(String),
@@ -144,18 +199,55 @@ pub enum Error {
         /// Number of elements in the vector.
         vec_dim: usize,
     },
+    /// The random sample rescaled for the sampled instrument outcome is negative.
+    #[error("numerical error: rescaled random sample should be non-negative, but it is {0}")]
+    NegativeRescaledSample(f64),
+    /// An observable is not a Hermitian matrix.
+    #[error("observable should be a Hermitian matrix")]
+    NonHermitianObservable,
     /// State is not normalized.
     #[error("numerical error: trace should be between 0 and 1, but it is {0}")]
     NotNormalized(f64),
+    /// Two operations that should act on the same number of qubits don't.
+    #[error("operations act on different numbers of qubits: {0} != {1}")]
+    OperationsQubitCountMismatch(usize, usize),
+    /// A parameter index is greater than the number of parameters.
+    #[error("parameter index out of bounds: {0}")]
+    ParameterIndexOutOfBounds(usize),
     /// A numerical error, such as a probability-0 event.
     #[error("numerical error: probability-0 event")]
     ProbabilityZeroEvent,
-    /// A qubit-id is greater than the number of qubits the simulation supports.
-    #[error("qubit id out of bounds: {0}")]
-    QubitIdOutOfBounds(usize),
+    /// A qubit index is greater than or equal to the number of qubits in the system.
+    #[error("qubit index {index} out of range for a system of {number_of_qubits} qubits")]
+    QubitIndexOutOfRange {
+        /// The offending qubit index.
+        index: usize,
+        /// Number of qubits in the system.
+        number_of_qubits: usize,
+    },
+    /// A qubit to be removed from the system is entangled with the rest of the system.
+    #[error("qubit {0} is entangled with the rest of the system")]
+    QubitIsEntangled(usize),
+    /// The number of qubits in a qubit mask doesn't match the number of qubits of the operation.
+    #[error("qubit mask targets {mask_qubits} qubits but the operation acts on {operation_qubits} qubits")]
+    QubitMaskMismatch {
+        /// Number of set bits in the qubit mask.
+        mask_qubits: usize,
+        /// Number of qubits the operation acts on.
+        operation_qubits: usize,
+    },
+    /// A qubit expected to be classical is not in a computational basis state.
+    #[error("qubit {0} is not in a computational basis state")]
+    QubitNotClassical(usize),
     /// Failure when building a `StateVector` from raw data.
     #[error("error when building `StateVector` from raw_data: {0}")]
     StateVectorTryFromError(String),
+    /// A system has too many qubits for the dimension of its state to fit in a `usize`.
+    #[error("a system of {0} qubits is too large to simulate")]
+    TooManyQubits(usize),
+    /// The theoretical change in trace dropped below the configured minimum.
+    #[error("simulation aborted: trace change {0} dropped below the configured minimum")]
+    TraceBelowThreshold(f64),
     /// Trace is not real
     #[error("state trace should be real since it represents a probability, but its imaginary part is: {0}")]
     TraceIsNotReal(f64),
@@ -168,6 +260,7 @@ impl Error {
             Error::ProbabilityZeroEvent
                 | Error::FailedToSampleInstrumentOutcome
                 | Error::FailedToSampleKrausOperators
+                | Error::TraceBelowThreshold(_)
         )
     }
 }
@@ -0,0 +1,17 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! This module contains linear algebra helpers shared by the `DensityMatrixSimulator`
+//! and the `StateVectorSimulator`.
+
+use crate::{SquareMatrix, TOLERANCE};
+
+/// Returns the von Neumann entropy -tr(ρ log₂ ρ), in bits, of the density matrix `ρ`.
+pub(crate) fn von_neumann_entropy(density_matrix: &SquareMatrix) -> f64 {
+    -density_matrix
+        .symmetric_eigenvalues()
+        .iter()
+        .filter(|eigenvalue| **eigenvalue > TOLERANCE)
+        .map(|eigenvalue| eigenvalue * eigenvalue.log2())
+        .sum::<f64>()
+}
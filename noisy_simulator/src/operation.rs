@@ -6,7 +6,8 @@
 
 #[cfg(test)]
 mod tests;
-use crate::{Error, SquareMatrix};
+use crate::{Error, SquareMatrix, TOLERANCE};
+use nalgebra::{DMatrix, DVector};
 
 /// A helper macro to write operations more conveniently.
 ///
@@ -150,4 +151,103 @@ impl Operation {
     pub fn number_of_qubits(&self) -> usize {
         self.number_of_qubits
     }
+
+    /// Returns an upper bound on the diamond-norm distance ‖Φ - Ψ‖◇ between this
+    /// channel Φ and `other` channel Ψ, computed from their Choi matrices J(Φ) and J(Ψ):
+    /// ‖J(Φ) - J(Ψ)‖₁ / d ≤ ‖Φ - Ψ‖◇ ≤ min(‖J(Φ) - J(Ψ)‖₁, 2)
+    /// where ‖⋅‖₁ is the trace norm and d = 2^`number_of_qubits`.
+    ///
+    /// This method returns the upper bound. Dividing it by d yields a lower bound, which is
+    /// tight for many common channels, e.g. Pauli channels. Computing the exact value
+    /// requires solving a semidefinite program.
+    pub fn diamond_distance(&self, other: &Operation) -> Result<f64, Error> {
+        if self.number_of_qubits != other.number_of_qubits {
+            return Err(Error::OperationsQubitCountMismatch(
+                self.number_of_qubits,
+                other.number_of_qubits,
+            ));
+        }
+        let choi_difference = self.choi_matrix() - other.choi_matrix();
+        let trace_norm: f64 = choi_difference
+            .symmetric_eigen()
+            .eigenvalues
+            .iter()
+            .map(|eigenvalue| eigenvalue.abs())
+            .sum();
+        Ok(trace_norm.min(2.0))
+    }
+
+    /// Returns the (unnormalized) Choi matrix of the operation:
+    /// Σᵢ vec(Kᵢ) ⋅ vec(Kᵢ)†
+    /// where Kᵢ are Kraus operators and † denotes the adjoint.
+    pub(crate) fn choi_matrix(&self) -> SquareMatrix {
+        self.kraus_operators
+            .iter()
+            .map(|k| {
+                let vectorized_k = vectorize(k);
+                &vectorized_k * &vectorized_k.adjoint()
+            })
+            .sum()
+    }
+
+    /// Returns the non-unitality defect of the operation:
+    /// ‖Σᵢ (Kᵢ Kᵢ†) - I‖
+    /// where Kᵢ are Kraus operators, † denotes the adjoint of the matrix and ‖⋅‖ is the
+    /// Frobenius norm. Unital channels, which preserve the maximally mixed state, return ~0.
+    #[must_use]
+    pub fn non_unitality(&self) -> f64 {
+        // Since we store the transposed Kraus operators kᵢ = Kᵢ^T, we have that
+        // Σᵢ (Kᵢ Kᵢ†) = (Σᵢ kᵢ† ⋅ kᵢ)^T, and transposing doesn't change the Frobenius norm.
+        let dim = 1 << self.number_of_qubits;
+        let kraus_sum: SquareMatrix = self.kraus_operators.iter().map(|k| k.adjoint() * k).sum();
+        (kraus_sum - SquareMatrix::identity(dim, dim)).norm()
+    }
+
+    /// Checks that the operation is trace preserving, i.e. that its Kraus operators satisfy
+    /// the completeness relation Σᵢ (Kᵢ† Kᵢ) = I within `TOLERANCE`. It also checks that the
+    /// Kraus operators and the effect matrix have consistent dimensions.
+    ///
+    /// Trace decreasing operations, such as the operations of an instrument's outcomes, are
+    /// valid operations that don't satisfy the completeness relation.
+    pub fn validate_completeness(&self) -> Result<(), Error> {
+        let dim = 1 << self.number_of_qubits;
+        if let Some(k) = self
+            .kraus_operators
+            .iter()
+            .find(|k| k.shape() != (dim, dim))
+        {
+            return Err(Error::InvalidState(format!(
+                "kraus operators should have dimensions {dim} x {dim}, but found {} x {}",
+                k.nrows(),
+                k.ncols()
+            )));
+        }
+        if self.effect_matrix.shape() != (dim, dim) {
+            return Err(Error::InvalidState(format!(
+                "effect matrix should have dimensions {dim} x {dim}, but it is {} x {}",
+                self.effect_matrix.nrows(),
+                self.effect_matrix.ncols()
+            )));
+        }
+
+        // The effect matrix stores (Σᵢ Kᵢ† Kᵢ)^T, and the identity is its own transpose.
+        let deviation = (&self.effect_matrix - SquareMatrix::identity(dim, dim)).norm();
+        if deviation > TOLERANCE {
+            return Err(Error::InvalidState(format!(
+                "kraus operators should satisfy Σᵢ Kᵢ† Kᵢ = I, but the sum deviates from the identity by {deviation} in Frobenius norm"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Stacks the columns of `matrix` into a single column vector.
+///
+/// Performance note: Typically vectorization stacks the
+/// rows of a matrix into a single column vector. But since we
+/// transposed all matrices until now, we stack the columns instead.
+///
+/// See `Operation::new` for more details.
+fn vectorize<T: nalgebra::Scalar + Copy>(matrix: &DMatrix<T>) -> DVector<T> {
+    DVector::<T>::from_iterator(matrix.len(), matrix.iter().copied())
 }
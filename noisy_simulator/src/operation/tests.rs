@@ -5,9 +5,9 @@ use nalgebra::dmatrix;
 use num_complex::{Complex, ComplexFloat};
 
 use crate::{
-    operation::{operation, Operation},
+    operation::{operation, vectorize, Operation},
     tests::assert_approx_eq,
-    SquareMatrix,
+    Error, SquareMatrix,
 };
 
 /// Constructs an operation using dense kraus matrices, to allow an exhaustive
@@ -33,6 +33,13 @@ fn dense_operation() -> Operation {
     .expect("operation should be valid")
 }
 
+#[test]
+fn check_vectorize() {
+    let m = nalgebra::dmatrix![1, 2, 3;
+                               4, 5, 6];
+    assert_eq!(nalgebra::dvector![1, 4, 2, 5, 3, 6], vectorize(&m));
+}
+
 #[test]
 fn check_operation_number_of_qubits_is_computed_correctly() {
     let op = operation!(
@@ -142,3 +149,129 @@ fn check_operation_matrix_is_computed_correctly() {
         assert_approx_eq(0., (x0 - x1).abs());
     }
 }
+
+#[test]
+fn check_non_unitality_of_bit_flip_is_zero() {
+    let op = operation!(
+        [
+            0.8, 0.;
+            0., 0.8;
+        ],
+        [
+            0., 0.6;
+            0.6, 0.;
+        ]
+    )
+    .expect("operation should be valid");
+
+    assert_approx_eq(0., op.non_unitality());
+}
+
+#[test]
+fn check_non_unitality_of_amplitude_damping_is_computed_correctly() {
+    // Amplitude damping with γ = 0.36: Σᵢ (Kᵢ Kᵢ†) = diag(1 + γ, 1 - γ).
+    let op = operation!(
+        [
+            1., 0.;
+            0., 0.8;
+        ],
+        [
+            0., 0.6;
+            0., 0.;
+        ]
+    )
+    .expect("operation should be valid");
+
+    assert_approx_eq(0.36 * std::f64::consts::SQRT_2, op.non_unitality());
+}
+
+#[test]
+fn check_completeness_of_amplitude_damping_is_valid() {
+    let op = operation!(
+        [
+            1., 0.;
+            0., 0.6;
+        ],
+        [
+            0., 0.8;
+            0., 0.;
+        ]
+    )
+    .expect("operation should be valid");
+
+    assert_eq!(Ok(()), op.validate_completeness());
+}
+
+#[test]
+fn check_completeness_fails_when_kraus_operators_do_not_sum_to_identity() {
+    // Σᵢ (Kᵢ† Kᵢ) = diag(1, 0.64 + 0.64).
+    let op = operation!(
+        [
+            1., 0.;
+            0., 0.8;
+        ],
+        [
+            0., 0.8;
+            0., 0.;
+        ]
+    )
+    .expect("operation should be valid");
+
+    assert!(matches!(
+        op.validate_completeness(),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn check_diamond_distance_of_equal_operations_is_zero() {
+    let op = dense_operation();
+    assert_approx_eq(
+        0.,
+        op.diamond_distance(&op)
+            .expect("operations should act on the same qubits"),
+    );
+}
+
+#[test]
+fn check_diamond_distance_of_orthogonal_unitaries_is_maximal() {
+    let identity = operation!([1., 0.; 0., 1.;]).expect("operation should be valid");
+    let x = operation!([0., 1.; 1., 0.;]).expect("operation should be valid");
+    assert_approx_eq(
+        2.,
+        identity
+            .diamond_distance(&x)
+            .expect("operations should act on the same qubits"),
+    );
+}
+
+#[test]
+fn check_diamond_distance_bounds_bit_flip_channel() {
+    // The diamond distance between the identity and a bit flip with probability p is 2p.
+    // The Choi bound yields 4p, and 4p / d = 2p is the matching lower bound.
+    let identity = operation!([1., 0.; 0., 1.;]).expect("operation should be valid");
+    let bit_flip = operation!(
+        [
+            0.8, 0.;
+            0., 0.8;
+        ],
+        [
+            0., 0.6;
+            0.6, 0.;
+        ]
+    )
+    .expect("operation should be valid");
+    let bound = identity
+        .diamond_distance(&bit_flip)
+        .expect("operations should act on the same qubits");
+    assert_approx_eq(4. * 0.36, bound);
+}
+
+#[test]
+fn check_diamond_distance_fails_on_different_number_of_qubits() {
+    let one_qubit = operation!([1., 0.; 0., 1.;]).expect("operation should be valid");
+    assert_eq!(
+        one_qubit.diamond_distance(&dense_operation()),
+        Err(crate::Error::OperationsQubitCountMismatch(1, 2))
+    );
+}
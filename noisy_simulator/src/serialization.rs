@@ -0,0 +1,30 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! This module contains the `serde` representations of the `nalgebra` types used by the
+//! simulators' states, for use with the `#[serde(with = "...")]` field attribute.
+
+/// Serializes a `ComplexVector` as a sequence of `[re, im]` pairs, which round-trips
+/// losslessly through formats that preserve `f64` values, like JSON.
+pub(crate) mod complex_vector {
+    use crate::ComplexVector;
+    use num_complex::Complex;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        vector: &ComplexVector,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(vector.iter().map(|entry| [entry.re, entry.im]))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ComplexVector, D::Error> {
+        let entries = Vec::<[f64; 2]>::deserialize(deserializer)?;
+        Ok(ComplexVector::from_iterator(
+            entries.len(),
+            entries.into_iter().map(|[re, im]| Complex::new(re, im)),
+        ))
+    }
+}
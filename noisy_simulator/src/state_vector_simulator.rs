@@ -7,14 +7,26 @@
 #[cfg(test)]
 mod tests;
 
+use num_complex::Complex;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::{
-    handle_error, instrument::Instrument, kernel::apply_kernel, operation::Operation,
+    density_matrix_simulator::{DensityMatrix, DensityMatrixSimulator},
+    handle_error,
+    instrument::Instrument,
+    kernel::{apply_kernel, default_chunk_size},
+    linalg::von_neumann_entropy,
+    operation::Operation,
     ComplexVector, Error, NoisySimulator, SquareMatrix, TOLERANCE,
 };
 
 /// A vector representing the state of a quantum system.
+///
+/// With the `serde` feature enabled, a `StateVector` can be serialized to checkpoint a
+/// simulation. Deserialization runs the same validation as `StateVector::try_from`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "StateVectorFields"))]
 pub struct StateVector {
     /// Dimension of the vector.
     dimension: usize,
@@ -22,10 +34,48 @@ pub struct StateVector {
     number_of_qubits: usize,
     /// Theoretical change in trace due to operations that have been applied so far.
     trace_change: f64,
+    /// Natural logarithm of `trace_change`, accumulated additively so that it doesn't
+    /// underflow over long circuits.
+    log_trace_change: f64,
     /// Vector storing the entries of the density matrix.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::complex_vector")
+    )]
     data: ComplexVector,
 }
 
+/// The raw fields of a `StateVector`, validated by `StateVector::try_from` on deserialization.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct StateVectorFields {
+    dimension: usize,
+    number_of_qubits: usize,
+    trace_change: f64,
+    #[serde(default)]
+    log_trace_change: Option<f64>,
+    #[serde(with = "crate::serialization::complex_vector")]
+    data: ComplexVector,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<StateVectorFields> for StateVector {
+    type Error = Error;
+
+    fn try_from(fields: StateVectorFields) -> Result<Self, Error> {
+        let mut state = StateVector::try_from(
+            fields.dimension,
+            fields.number_of_qubits,
+            fields.trace_change,
+            fields.data,
+        )?;
+        if let Some(log_trace_change) = fields.log_trace_change {
+            state.log_trace_change = log_trace_change;
+        }
+        Ok(state)
+    }
+}
+
 impl StateVector {
     fn new(number_of_qubits: usize) -> Self {
         let dimension = 1 << number_of_qubits;
@@ -35,6 +85,7 @@ impl StateVector {
             dimension,
             number_of_qubits,
             trace_change: 1.0,
+            log_trace_change: 0.0,
             data: state_vector,
         }
     }
@@ -49,7 +100,7 @@ impl StateVector {
         trace_change: f64,
         data: ComplexVector,
     ) -> Result<Self, Error> {
-        if 1 << number_of_qubits != dimension {
+        if number_of_qubits >= usize::BITS as usize || 1 << number_of_qubits != dimension {
             return Err(Error::StateVectorTryFromError(format!(
                 "the system has {number_of_qubits} qubits and the state vector has dimension {dimension} but 2 ^ {number_of_qubits} != {dimension}"
             )));
@@ -69,6 +120,7 @@ impl StateVector {
             dimension,
             number_of_qubits,
             trace_change,
+            log_trace_change: trace_change.ln(),
             data,
         })
     }
@@ -91,9 +143,290 @@ impl StateVector {
         self.number_of_qubits
     }
 
-    /// Returns `true` if the squared L2 norm of the matrix is 1.
-    fn is_normalized(&self) -> bool {
-        (self.norm_squared() - 1.0).abs() <= TOLERANCE
+    /// Returns the probability |⟨i|ψ⟩|² of measuring the computational basis state |i⟩,
+    /// where i is `basis_index` and qubit k corresponds to the k-th bit of i.
+    pub fn basis_state_probability(&self, basis_index: usize) -> Result<f64, Error> {
+        if basis_index >= self.dimension {
+            return Err(Error::BasisIndexOutOfBounds(basis_index, self.dimension));
+        }
+        Ok(self.data[basis_index].norm_sqr())
+    }
+
+    /// Returns the probabilities of measuring each computational basis state, indexed as
+    /// in `basis_state_probability`.
+    #[must_use]
+    pub fn populations(&self) -> Vec<f64> {
+        self.data.iter().map(Complex::norm_sqr).collect()
+    }
+
+    /// Returns the density matrix |ψ⟩⟨ψ| of the state as a `dimension` x `dimension` matrix,
+    /// whose entry at row i and column j is ψᵢ ψⱼ*.
+    #[must_use]
+    pub fn density_matrix(&self) -> SquareMatrix {
+        &self.data * self.data.adjoint()
+    }
+
+    /// Returns the expectation value ⟨ψ|O|ψ⟩ of the `observable` O acting on `qubits`.
+    pub fn expectation_value(
+        &self,
+        observable: &SquareMatrix,
+        qubits: &[usize],
+    ) -> Result<f64, Error> {
+        let mut buffer = self.data.clone();
+        self.expectation_value_with_buffer(observable, qubits, &mut buffer)
+    }
+
+    /// Returns the expectation values of a list of `(observable, qubits)` terms, e.g. the
+    /// terms of a Hamiltonian. A single buffer is reused for all the terms.
+    pub fn expectation_values(
+        &self,
+        terms: &[(SquareMatrix, Vec<usize>)],
+    ) -> Result<Vec<f64>, Error> {
+        let mut buffer = self.data.clone();
+        terms
+            .iter()
+            .map(|(observable, qubits)| {
+                buffer.copy_from(&self.data);
+                self.expectation_value_with_buffer(observable, qubits, &mut buffer)
+            })
+            .collect()
+    }
+
+    /// Returns the reduced density matrix of the given `qubits`, tracing out all the other
+    /// qubits of the system. The returned matrix has dimension 2^k x 2^k, where k is the
+    /// number of `qubits`, and follows the little-endian convention of `apply_kernel`:
+    /// the i-th qubit in `qubits` corresponds to the i-th bit of the matrix indices.
+    pub fn reduced_density_matrix(&self, qubits: &[usize]) -> Result<SquareMatrix, Error> {
+        if let Some(id) = qubits.iter().find(|id| **id >= self.number_of_qubits) {
+            return Err(Error::QubitIndexOutOfRange {
+                index: *id,
+                number_of_qubits: self.number_of_qubits,
+            });
+        }
+
+        // Indices of the full state vector corresponding to each reduced basis state.
+        let reduced_dimension = 1 << qubits.len();
+        let offsets: Vec<usize> = (0..reduced_dimension)
+            .map(|idx| {
+                qubits
+                    .iter()
+                    .enumerate()
+                    .map(|(bit, id)| ((idx >> bit) & 1) << id)
+                    .sum()
+            })
+            .collect();
+        let mask: usize = qubits.iter().map(|id| 1 << id).sum();
+
+        let mut reduced_density_matrix = SquareMatrix::zeros(reduced_dimension, reduced_dimension);
+        for environment in (0..self.dimension).filter(|idx| idx & mask == 0) {
+            for (row, row_offset) in offsets.iter().enumerate() {
+                let amplitude = self.data[environment | row_offset];
+                for (col, col_offset) in offsets.iter().enumerate() {
+                    reduced_density_matrix[(row, col)] +=
+                        amplitude * self.data[environment | col_offset].conj();
+                }
+            }
+        }
+        Ok(reduced_density_matrix)
+    }
+
+    /// Returns the coherent information S(ρ') - S(ρₑ), in bits, of `operation` acting on
+    /// `qubits` for this input state, where ρ' is the output state of `qubits` and ρₑ is
+    /// the state of the environment of the channel.
+    ///
+    /// Purification convention: the qubits of the system not listed in `qubits` act as the
+    /// reference system R, so this state is the purification |ψ⟩ᵣₐ of the channel input ρₐ.
+    /// Since the global state of the reference, the output and the environment is pure,
+    /// S(ρₑ) = S(ρ'ᵣₐ), which is computed from the Kraus operators without building the
+    /// environment explicitly. If all the qubits are listed, the input is a pure state.
+    ///
+    /// Performance note: this method builds the 2^n x 2^n output density matrix of the
+    /// whole system, where n is `number_of_qubits`.
+    pub fn coherent_information(
+        &self,
+        operation: &Operation,
+        qubits: &[usize],
+    ) -> Result<f64, Error> {
+        self.coherent_information_with_tolerance(operation, qubits, TOLERANCE)
+    }
+
+    /// Like `coherent_information`, but returns `Error::ProbabilityZeroEvent` if the trace
+    /// of the channel output is below `tolerance`.
+    fn coherent_information_with_tolerance(
+        &self,
+        operation: &Operation,
+        qubits: &[usize],
+        tolerance: f64,
+    ) -> Result<f64, Error> {
+        if let Some(id) = qubits.iter().find(|id| **id >= self.number_of_qubits) {
+            return Err(Error::QubitIndexOutOfRange {
+                index: *id,
+                number_of_qubits: self.number_of_qubits,
+            });
+        }
+        let chunk_size = default_chunk_size(self.dimension);
+        let all_qubits: Vec<usize> = (0..self.number_of_qubits).collect();
+        let reduced_dimension = 1 << qubits.len();
+        let mut output = SquareMatrix::zeros(reduced_dimension, reduced_dimension);
+        let mut output_with_reference = SquareMatrix::zeros(self.dimension, self.dimension);
+        for kraus_operator in operation.kraus_operators() {
+            let mut branch = self.clone();
+            apply_kernel(&mut branch.data, kraus_operator, qubits, chunk_size)?;
+            output += branch.reduced_density_matrix(qubits)?;
+            output_with_reference += branch.reduced_density_matrix(&all_qubits)?;
+        }
+
+        let trace = output.trace().re;
+        if trace < tolerance {
+            return Err(Error::ProbabilityZeroEvent);
+        }
+        Ok(von_neumann_entropy(&(output / Complex::from(trace)))
+            - von_neumann_entropy(&(output_with_reference / Complex::from(trace))))
+    }
+
+    /// Returns the density matrix |ψ⟩⟨ψ| of this state |ψ⟩, with the same `trace_change`.
+    fn to_density_matrix(&self) -> Result<DensityMatrix, Error> {
+        let mut data = ComplexVector::zeros(self.dimension * self.dimension);
+        for row in 0..self.dimension {
+            for col in 0..self.dimension {
+                data[self.dimension * row + col] = self.data[row] * self.data[col].conj();
+            }
+        }
+        DensityMatrix::try_from(
+            self.dimension,
+            self.number_of_qubits,
+            self.trace_change,
+            data,
+        )
+    }
+
+    /// Returns the fidelity F(ψ, φ) = |⟨ψ|φ⟩|² between this state |ψ⟩ and `other` state |φ⟩.
+    pub fn fidelity(&self, other: &StateVector) -> Result<f64, Error> {
+        self.check_same_system(other)?;
+        Ok(self.data.dotc(&other.data).norm_sqr())
+    }
+
+    /// Returns the trace distance ½‖ψ - φ‖₁ between the density matrices of this state |ψ⟩
+    /// and `other` state |φ⟩. For pure states it is given by √(1 - F(ψ, φ)).
+    pub fn trace_distance(&self, other: &StateVector) -> Result<f64, Error> {
+        Ok((1.0 - self.fidelity(other)?).max(0.0).sqrt())
+    }
+
+    /// Returns the Hilbert-Schmidt distance Tr((ψ - φ)²) between the density matrices of this
+    /// state |ψ⟩ and `other` state |φ⟩. For pure states it is given by 2 (1 - F(ψ, φ)).
+    pub fn hilbert_schmidt_distance(&self, other: &StateVector) -> Result<f64, Error> {
+        Ok(2.0 * (1.0 - self.fidelity(other)?))
+    }
+
+    /// Checks that `other` represents a system with the same number of qubits.
+    fn check_same_system(&self, other: &StateVector) -> Result<(), Error> {
+        if self.number_of_qubits != other.number_of_qubits || self.dimension != other.dimension {
+            return Err(Error::InvalidState(format!(
+                "states should represent the same system, but they have {} and {} qubits",
+                self.number_of_qubits, other.number_of_qubits
+            )));
+        }
+        Ok(())
+    }
+
+    /// Computes ⟨ψ|O|ψ⟩ using `buffer`, which must contain a copy of the state vector.
+    fn expectation_value_with_buffer(
+        &self,
+        observable: &SquareMatrix,
+        qubits: &[usize],
+        buffer: &mut ComplexVector,
+    ) -> Result<f64, Error> {
+        if observable.nrows() != 1 << qubits.len() || observable.ncols() != 1 << qubits.len() {
+            return Err(Error::InvalidState(format!(
+                "observable of dimension ({}, {}) can't act on {} qubits",
+                observable.nrows(),
+                observable.ncols(),
+                qubits.len()
+            )));
+        }
+        if let Some(id) = qubits.iter().find(|id| **id >= self.number_of_qubits) {
+            return Err(Error::QubitIndexOutOfRange {
+                index: *id,
+                number_of_qubits: self.number_of_qubits,
+            });
+        }
+        // `apply_kernel` expects transposed matrices, see `kernel.rs` for details.
+        apply_kernel(
+            buffer,
+            &observable.transpose(),
+            qubits,
+            default_chunk_size(self.dimension),
+        )?;
+        Ok(self.data.dotc(buffer).re)
+    }
+
+    /// Appends `count` qubits in the |0⟩ state as the most significant qubits of the system.
+    /// Returns `Error::TooManyQubits` and leaves the state untouched if the dimension of the
+    /// grown system doesn't fit in a `usize`.
+    fn add_qubits(&mut self, count: usize) -> Result<(), Error> {
+        let number_of_qubits = self.number_of_qubits.saturating_add(count);
+        let dimension = u32::try_from(number_of_qubits)
+            .ok()
+            .and_then(|n| 1_usize.checked_shl(n))
+            .ok_or(Error::TooManyQubits(number_of_qubits))?;
+        self.number_of_qubits = number_of_qubits;
+        self.dimension = dimension;
+        self.data
+            .resize_vertically_mut(self.dimension, Complex::new(0.0, 0.0));
+        Ok(())
+    }
+
+    /// Swaps the states of qubits `a` and `b` by permuting the entries of the state vector.
+    fn swap_qubits(&mut self, a: usize, b: usize) {
+        let (a_mask, b_mask) = (1 << a, 1 << b);
+        for idx in (0..self.dimension).filter(|idx| idx & a_mask != 0 && idx & b_mask == 0) {
+            self.data.swap_rows(idx, idx ^ a_mask ^ b_mask);
+        }
+    }
+
+    /// Removes `qubit` from the system, halving its dimension. The qubits with ids greater
+    /// than `qubit` are renumbered, their ids decrease by one.
+    ///
+    /// Removing a qubit is only lossless if the qubit is not entangled with the rest of the
+    /// system, i.e. if the purity of its reduced state is 1 up to `tolerance`. Otherwise
+    /// `Error::QubitIsEntangled` is returned and the state is left untouched.
+    fn remove_qubit(&mut self, qubit: usize, tolerance: f64) -> Result<(), Error> {
+        let reduced_density_matrix = self.reduced_density_matrix(&[qubit])?;
+        // Since ρ is Hermitian, Tr(ρ²) = Σᵢⱼ |ρᵢⱼ|².
+        if (1.0 - reduced_density_matrix.norm_squared()).abs() > tolerance {
+            return Err(Error::QubitIsEntangled(qubit));
+        }
+
+        // Since ρ = |χ⟩⟨χ|, each column j of ρ is χ scaled by χⱼ*. We take the column with
+        // the largest diagonal entry |χⱼ|² to minimize numerical errors.
+        let col =
+            usize::from(reduced_density_matrix[(1, 1)].re > reduced_density_matrix[(0, 0)].re);
+        let norm = reduced_density_matrix[(col, col)].re.sqrt();
+        let qubit_state = [
+            reduced_density_matrix[(0, col)] / norm,
+            reduced_density_matrix[(1, col)] / norm,
+        ];
+
+        // |ψ⟩ = |φ⟩ ⊗ |χ⟩, so the remaining state is |φ⟩ = ⟨χ|ψ⟩.
+        let low_mask = (1 << qubit) - 1;
+        let dimension = self.dimension >> 1;
+        let data = ComplexVector::from_iterator(
+            dimension,
+            (0..dimension).map(|idx| {
+                let zero_idx = (idx & low_mask) | ((idx & !low_mask) << 1);
+                qubit_state[0].conj() * self.data[zero_idx]
+                    + qubit_state[1].conj() * self.data[zero_idx | (1 << qubit)]
+            }),
+        );
+        self.data = data;
+        self.dimension = dimension;
+        self.number_of_qubits -= 1;
+        Ok(())
+    }
+
+    /// Returns `true` if the squared L2 norm of the matrix is 1, up to `tolerance`.
+    fn is_normalized(&self, tolerance: f64) -> bool {
+        (self.norm_squared() - 1.0).abs() <= tolerance
     }
 
     /// Returns the squared L2 norm of the matrix.
@@ -104,19 +437,40 @@ impl StateVector {
     /// Return theoretical change in trace due to operations that have been applied so far.
     /// In reality, the density matrix is always renormalized after instruments / operations
     /// have been applied.
+    ///
+    /// Note: over long circuits with many low-probability operations the product of the
+    /// changes in trace can underflow to 0. Use `log_trace_change` in that case.
     #[must_use]
     pub fn trace_change(&self) -> f64 {
         self.trace_change
     }
 
+    /// Returns the natural logarithm of `trace_change`. Since it is accumulated as a sum of
+    /// logarithms, it stays finite when `trace_change` underflows to 0.
+    #[must_use]
+    pub fn log_trace_change(&self) -> f64 {
+        self.log_trace_change
+    }
+
+    /// Multiplies `trace_change` by `factor`.
+    fn scale_trace_change(&mut self, factor: f64) {
+        self.trace_change *= factor;
+        self.log_trace_change += factor.ln();
+    }
+
     /// Renormalizes the matrix such that the trace is 1.
-    fn renormalize(&mut self) -> Result<(), Error> {
-        self.renormalize_with_norm_squared(self.norm_squared())
+    fn renormalize(&mut self, tolerance: f64) -> Result<(), Error> {
+        self.renormalize_with_norm_squared(self.norm_squared(), tolerance)
     }
 
     /// Renormalizes the matrix such that the trace is 1. Uses a precomputed `norm_squared`.
-    fn renormalize_with_norm_squared(&mut self, norm_squared: f64) -> Result<(), Error> {
-        if norm_squared < TOLERANCE {
+    /// Returns `Error::ProbabilityZeroEvent` if `norm_squared` is below `tolerance`.
+    fn renormalize_with_norm_squared(
+        &mut self,
+        norm_squared: f64,
+        tolerance: f64,
+    ) -> Result<(), Error> {
+        if norm_squared < tolerance {
             return Err(Error::ProbabilityZeroEvent);
         }
         let renormalization_factor = 1.0 / norm_squared.sqrt();
@@ -129,41 +483,164 @@ impl StateVector {
         &self,
         effect_matrix: &SquareMatrix,
         qubits: &[usize],
+        chunk_size: usize,
     ) -> Result<f64, Error> {
         let mut state_copy = self.data.clone();
-        apply_kernel(&mut state_copy, effect_matrix, qubits)?;
+        apply_kernel(&mut state_copy, effect_matrix, qubits, chunk_size)?;
         Ok(self.data.dotc(&state_copy).re)
     }
 
+    /// Returns the expectation values `[⟨X⟩, ⟨Y⟩, ⟨Z⟩]` of each qubit, computed from the
+    /// single-qubit reduced density matrices in a single sweep over the state vector.
+    fn single_qubit_pauli_expectations(&self) -> Vec<[f64; 3]> {
+        let mut expectations = vec![[0.0; 3]; self.number_of_qubits];
+        for (idx, amplitude) in self.data.iter().enumerate() {
+            for (qubit, [x, y, z]) in expectations.iter_mut().enumerate() {
+                let mask = 1 << qubit;
+                if idx & mask == 0 {
+                    // Contribution to the coherence ⟨0|ρ|1⟩ of the reduced density matrix.
+                    let coherence = amplitude * self.data[idx | mask].conj();
+                    *x += 2.0 * coherence.re;
+                    *y -= 2.0 * coherence.im;
+                    *z += amplitude.norm_sqr();
+                } else {
+                    *z -= amplitude.norm_sqr();
+                }
+            }
+        }
+        expectations
+    }
+
+    /// Returns the variance `⟨O²⟩ - ⟨O⟩²` of a Hermitian `observable` acting on `qubits`.
+    fn expectation_variance(
+        &self,
+        observable: &SquareMatrix,
+        qubits: &[usize],
+        chunk_size: usize,
+    ) -> Result<f64, Error> {
+        // `apply_kernel` expects transposed matrices, see `kernel.rs` for details.
+        let mut state_copy = self.data.clone();
+        apply_kernel(&mut state_copy, &observable.transpose(), qubits, chunk_size)?;
+        let expectation = self.data.dotc(&state_copy).re;
+        // Since O is Hermitian, ⟨O²⟩ = ⟨ψ|O† O|ψ⟩ = ‖O|ψ⟩‖².
+        Ok(state_copy.norm_squared() - expectation * expectation)
+    }
+
+    /// Returns the `alpha`-stabilizer Rényi entropy of the state, computed from the
+    /// expectation values of all the 4^n Pauli strings. Pauli strings whose normalized
+    /// squared expectation value is below `tolerance` are skipped.
+    fn stabilizer_renyi_entropy(&self, alpha: f64, tolerance: f64) -> f64 {
+        // For the Pauli string P = i^|x & z| ⋅ X^x ⋅ Z^z we have that
+        // P|i⟩ = i^|x & z| ⋅ (-1)^|i & z| ⋅ |i ⊕ x⟩, and ⟨ψ|P|ψ⟩² only depends on x and z.
+        // The squared expectation values are normalized into a distribution Ξ_P.
+        #[allow(clippy::cast_precision_loss)]
+        let dimension = self.dimension as f64;
+        let mut entropy_sum = 0.0;
+        for x in 0..self.dimension {
+            for z in 0..self.dimension {
+                let mut expectation = Complex::new(0.0, 0.0);
+                for (idx, amplitude) in self.data.iter().enumerate() {
+                    let term = self.data[idx ^ x].conj() * amplitude;
+                    if (idx & z).count_ones() % 2 == 0 {
+                        expectation += term;
+                    } else {
+                        expectation -= term;
+                    }
+                }
+                let probability = expectation.norm_sqr() / dimension;
+                if probability < tolerance {
+                    continue;
+                }
+                entropy_sum += if (alpha - 1.0).abs() < tolerance {
+                    -probability * probability.ln()
+                } else {
+                    probability.powf(alpha)
+                };
+            }
+        }
+
+        if (alpha - 1.0).abs() < tolerance {
+            entropy_sum - dimension.ln()
+        } else {
+            entropy_sum.ln() / (1.0 - alpha) - dimension.ln()
+        }
+    }
+
+    /// Returns the overlap `⟨ψ₀ ⊗ ψ₁ ⊗ … | current⟩` between the product state given by
+    /// one single-qubit state per qubit and the current state, without building the
+    /// full product-state vector.
+    fn product_state_overlap(
+        &self,
+        single_qubit_states: &[[Complex<f64>; 2]],
+        tolerance: f64,
+    ) -> Result<Complex<f64>, Error> {
+        if single_qubit_states.len() != self.number_of_qubits {
+            return Err(Error::InvalidState(format!(
+                "expected {} single-qubit states but got {}",
+                self.number_of_qubits,
+                single_qubit_states.len()
+            )));
+        }
+        if let Some(qubit) = single_qubit_states
+            .iter()
+            .position(|[a, b]| (a.norm_sqr() + b.norm_sqr() - 1.0).abs() > tolerance)
+        {
+            return Err(Error::InvalidState(format!(
+                "single-qubit state of qubit {qubit} is not normalized"
+            )));
+        }
+
+        let mut overlap = Complex::new(0.0, 0.0);
+        for (idx, amplitude) in self.data.iter().enumerate() {
+            let mut product_amplitude = Complex::new(1.0, 0.0);
+            for (qubit, state) in single_qubit_states.iter().enumerate() {
+                product_amplitude *= state[(idx >> qubit) & 1];
+            }
+            overlap += product_amplitude.conj() * amplitude;
+        }
+        Ok(overlap)
+    }
+
+    /// Applies one of the `kraus_operators`, sampled according to `random_sample`, and
+    /// returns its index together with the number of Kraus operators whose probability was
+    /// computed to sample it. If `renormalize` is false, the state is left with the norm it has
+    /// after applying the sampled Kraus operator. Kraus operators with a probability below
+    /// `tolerance` are never sampled.
+    #[allow(clippy::too_many_arguments)]
     fn sample_kraus_operators(
         &mut self,
         kraus_operators: &[SquareMatrix],
         qubits: &[usize],
         renormalization_factor: f64,
         random_sample: f64,
-    ) -> Result<(), Error> {
+        chunk_size: usize,
+        renormalize: bool,
+        tolerance: f64,
+    ) -> Result<(usize, usize), Error> {
         let mut summed_probability = 0.0;
         let mut last_non_zero_probability = 0.0;
         let mut last_non_zero_probability_index = 0;
 
         for (i, kraus_operator) in kraus_operators.iter().enumerate() {
             let mut state_copy = self.data.clone();
-            apply_kernel(&mut state_copy, kraus_operator, qubits)?;
+            apply_kernel(&mut state_copy, kraus_operator, qubits, chunk_size)?;
             let norm_squared = state_copy.norm_squared();
             let p = norm_squared / renormalization_factor;
             summed_probability += p;
-            if p >= TOLERANCE {
+            if p >= tolerance {
                 last_non_zero_probability = p;
                 last_non_zero_probability_index = i;
                 if summed_probability > random_sample {
                     self.data = state_copy;
-                    self.renormalize_with_norm_squared(norm_squared)?;
-                    return Ok(());
+                    if renormalize {
+                        self.renormalize_with_norm_squared(norm_squared, tolerance)?;
+                    }
+                    return Ok((i, i + 1));
                 }
             }
         }
 
-        if summed_probability + TOLERANCE > random_sample && last_non_zero_probability >= TOLERANCE
+        if summed_probability + tolerance > random_sample && last_non_zero_probability >= tolerance
         {
             return Err(Error::FailedToSampleKrausOperators);
         }
@@ -172,10 +649,74 @@ impl StateVector {
             &mut self.data,
             &kraus_operators[last_non_zero_probability_index],
             qubits,
+            chunk_size,
         )?;
 
-        self.renormalize()
+        if renormalize {
+            self.renormalize(tolerance)?;
+        }
+        Ok((last_non_zero_probability_index, kraus_operators.len()))
+    }
+}
+
+/// Returns the squared norm of the unnormalized state obtained by applying the selected
+/// outcome of each of the `steps` to `data`, branching over the Kraus operators of each
+/// selected outcome. Branches with a squared norm below `tolerance` are pruned.
+fn branch_probability(
+    data: &ComplexVector,
+    steps: &[(Instrument, Vec<usize>, usize)],
+    chunk_size: usize,
+    tolerance: f64,
+) -> Result<f64, Error> {
+    let Some(((instrument, qubits, outcome), remaining_steps)) = steps.split_first() else {
+        return Ok(data.norm_squared());
+    };
+    let mut probability = 0.0;
+    for kraus_operator in instrument.operation(*outcome).kraus_operators() {
+        let mut branch = data.clone();
+        apply_kernel(&mut branch, kraus_operator, qubits, chunk_size)?;
+        if branch.norm_squared() >= tolerance {
+            probability += branch_probability(&branch, remaining_steps, chunk_size, tolerance)?;
+        }
     }
+    Ok(probability)
+}
+
+/// Returns `true` if the matrix is square and Hermitian up to `tolerance`.
+fn is_hermitian(matrix: &SquareMatrix, tolerance: f64) -> bool {
+    matrix.is_square()
+        && matrix
+            .iter()
+            .zip(matrix.adjoint().iter())
+            .all(|(elt, adjoint_elt)| (elt - adjoint_elt).norm() <= tolerance)
+}
+
+/// A Kraus operator sampled by a `StateVectorSimulator`, reported to its trace observer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceEvent<'a> {
+    /// The Kraus operators that were sampled from, as returned by `Operation::kraus_operators`.
+    pub kraus_operators: &'a [SquareMatrix],
+    /// The qubit ids the Kraus operators were applied to.
+    pub qubits: &'a [usize],
+    /// The index of the sampled Kraus operator in `kraus_operators`.
+    pub kraus_index: usize,
+    /// The observed outcome, if the Kraus operators belong to a sampled instrument outcome.
+    pub outcome: Option<usize>,
+    /// The theoretical change in trace after applying the sampled Kraus operator.
+    pub trace_change: f64,
+}
+
+/// Work done by a `StateVectorSimulator` along a trajectory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShotStats {
+    /// Number of times a Kraus operator was sampled by an operation or an instrument.
+    pub kraus_samples: usize,
+    /// Number of times the state was renormalized.
+    pub renormalizations: usize,
+    /// Number of instrument outcomes and Kraus operators whose probability was computed
+    /// until one of them was sampled. Unlike the other counters, this depends on the
+    /// sampled branch.
+    pub probability_evaluations: usize,
 }
 
 /// A quantum circuit simulator using a state vector.
@@ -187,86 +728,856 @@ pub struct StateVectorSimulator {
     dimension: usize,
     /// Random number generator used for probabilistic operations.
     rng: StdRng,
+    /// If `trace_change` drops below this threshold the simulation is aborted.
+    min_trace_change: f64,
+    /// Number of state entries processed per chunk when applying kernels.
+    parallel_chunk_size: usize,
+    /// If true, out of range qubit ids grow the system instead of returning an error.
+    auto_grow: bool,
+    /// If true, inconsistencies in the probability accounting when sampling instruments
+    /// return an error instead of being clamped away.
+    strict_sampling: bool,
+    /// Callback notified of every Kraus operator sampled by the simulator.
+    trace_observer: Option<Box<dyn FnMut(TraceEvent) + Send>>,
+    /// Work done by the simulator since the last reset.
+    shot_stats: ShotStats,
+    /// Tolerance used for normalization checks, probability-zero detection, and sampling.
+    tolerance: f64,
+    /// Squared norm the state was left with by `apply_operation_raw` without
+    /// renormalization, or 1 if the state is normalized. It is divided out of the next
+    /// effect probability, so that it is not counted twice into `trace_change`.
+    pending_norm_squared: f64,
 }
 
 impl StateVectorSimulator {
-    fn check_out_of_bounds_qubits(&self, qubits: &[usize]) -> Result<(), Error> {
-        let number_of_qubits = self.state.as_ref()?.number_of_qubits;
-        if let Some(id) = qubits.iter().find(|id| **id >= number_of_qubits) {
-            Err(Error::QubitIdOutOfBounds(*id))
-        } else {
-            Ok(())
-        }
-    }
-}
-
-impl NoisySimulator for StateVectorSimulator {
-    type State = StateVector;
-
-    /// Creates a new `StateVectorSimulator`.
-    fn new(number_of_qubits: usize) -> Self {
+    /// Creates a new `StateVectorSimulator` that draws all its random samples from `rng`.
+    ///
+    /// Two simulators created from generators in the same state evolve through exactly the
+    /// same trajectories, which allows replaying a failing run for debugging.
+    #[must_use]
+    pub fn new_with_rng(number_of_qubits: usize, rng: StdRng) -> Self {
         let state_vector = StateVector::new(number_of_qubits);
         let dimension = state_vector.dimension();
         Self {
             state: Ok(state_vector),
             dimension,
-            rng: StdRng::from_entropy(),
+            rng,
+            min_trace_change: 0.0,
+            parallel_chunk_size: default_chunk_size(dimension),
+            auto_grow: false,
+            strict_sampling: false,
+            trace_observer: None,
+            shot_stats: ShotStats::default(),
+            tolerance: TOLERANCE,
+            pending_norm_squared: 1.0,
         }
     }
 
-    /// Creates a new `StateVectorSimulator` with a given seed for its random number generator.
-    fn new_with_seed(number_of_qubits: usize, seed: u64) -> Self {
-        let state_vector = StateVector::new(number_of_qubits);
-        let dimension = state_vector.dimension();
+    /// Creates a new `StateVectorSimulator` in the given `state`, used to convert between
+    /// simulator backends.
+    pub(crate) fn from_state(state: StateVector, rng: StdRng, min_trace_change: f64) -> Self {
+        let dimension = state.dimension();
         Self {
-            state: Ok(state_vector),
+            state: Ok(state),
             dimension,
-            rng: StdRng::seed_from_u64(seed),
+            rng,
+            min_trace_change,
+            parallel_chunk_size: default_chunk_size(dimension),
+            auto_grow: false,
+            strict_sampling: false,
+            trace_observer: None,
+            shot_stats: ShotStats::default(),
+            tolerance: TOLERANCE,
+            pending_norm_squared: 1.0,
         }
     }
 
-    /// Apply an operation to given qubit ids.
-    fn apply_operation(&mut self, operation: &Operation, qubits: &[usize]) -> Result<(), Error> {
-        self.check_out_of_bounds_qubits(qubits)?;
+    /// Forks the simulation: the fork starts in the same state and configuration, but draws
+    /// its random samples from a generator seeded by advancing this simulator's generator,
+    /// so the two copies follow independent trajectories. The trace observer is not copied.
+    #[must_use]
+    pub fn fork(&mut self) -> Self {
+        let rng = StdRng::seed_from_u64(self.rng.gen());
+        self.copy_with_rng(rng)
+    }
 
-        let renormalization_factor = self
-            .state
+    /// Returns a copy of this simulator, without its trace observer, that draws its random
+    /// samples from `rng`.
+    fn copy_with_rng(&self, rng: StdRng) -> Self {
+        Self {
+            state: self.state.clone(),
+            dimension: self.dimension,
+            rng,
+            min_trace_change: self.min_trace_change,
+            parallel_chunk_size: self.parallel_chunk_size,
+            auto_grow: self.auto_grow,
+            strict_sampling: self.strict_sampling,
+            trace_observer: None,
+            shot_stats: self.shot_stats,
+            tolerance: self.tolerance,
+            pending_norm_squared: self.pending_norm_squared,
+        }
+    }
+
+    /// Converts this simulator into a `DensityMatrixSimulator` in the state |ψ⟩⟨ψ|, where
+    /// |ψ⟩ is the current state. The number of qubits, `trace_change`, the random number
+    /// generator, and the minimum trace change are preserved.
+    ///
+    /// Memory note: the density matrix has `dimension` ^ 2 entries.
+    pub fn into_density_matrix_simulator(self) -> Result<DensityMatrixSimulator, Error> {
+        let density_matrix = self.state?.to_density_matrix()?;
+        Ok(DensityMatrixSimulator::from_state(
+            density_matrix,
+            self.rng,
+            self.min_trace_change,
+        ))
+    }
+
+    /// Sets the tolerance used for normalization checks, probability-zero detection, the
+    /// validation of the matrices passed to the simulator, and the cutoffs when sampling
+    /// Kraus operators and instrument outcomes. The default is 1e-12.
+    ///
+    /// A looser tolerance accepts less accurate states. A tighter tolerance lets the
+    /// simulator sample branches with smaller probabilities, where it would otherwise
+    /// return `Error::ProbabilityZeroEvent`.
+    #[must_use]
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Appends `count` qubits in the |0⟩ state to the system. The new qubits get the ids
+    /// `number_of_qubits..number_of_qubits + count`, so they become the most significant
+    /// bits of the state vector indices and the amplitudes of the existing basis states
+    /// are preserved.
+    ///
+    /// Memory note: each added qubit doubles the size of the state vector.
+    /// Errors: returns `Error::TooManyQubits` if the dimension of the grown system doesn't
+    /// fit in a `usize`.
+    pub fn add_qubits(&mut self, count: usize) -> Result<(), Error> {
+        let state = self.state.as_mut()?;
+        state.add_qubits(count)?;
+        self.dimension = state.dimension;
+        Ok(())
+    }
+
+    /// Allocates a new qubit in the |0⟩ state and returns its id, which is the number of
+    /// qubits of the system before the allocation.
+    ///
+    /// Memory note: each allocated qubit doubles the size of the state vector.
+    pub fn allocate_qubit(&mut self) -> Result<usize, Error> {
+        let id = self.state.as_ref()?.number_of_qubits;
+        self.add_qubits(1)?;
+        Ok(id)
+    }
+
+    /// Deallocates `qubit`, halving the size of the state vector. The qubits with ids
+    /// greater than `qubit` are renumbered, their ids decrease by one.
+    ///
+    /// Since the state of the system is pure, deallocation is only possible if `qubit` is
+    /// not entangled with the rest of the system, e.g. after uncomputing an ancilla.
+    /// Otherwise `Error::QubitIsEntangled` is returned and the state is left untouched.
+    pub fn deallocate_qubit(&mut self, qubit: usize) -> Result<(), Error> {
+        self.check_qubits(&[qubit])?;
+        let state = self.state.as_mut()?;
+        state.remove_qubit(qubit, self.tolerance)?;
+        self.dimension = state.dimension;
+        Ok(())
+    }
+
+    /// If `auto_grow` is true, applying an operation or an instrument to a qubit id greater
+    /// or equal than `number_of_qubits` calls `add_qubits` to grow the system up to that id,
+    /// instead of returning `Error::QubitIndexOutOfRange`. The default is false.
+    ///
+    /// Memory note: since the size of the state vector is exponential in the number of
+    /// qubits, a single large qubit id can exhaust the memory of the system. Ids whose
+    /// grown system would have a dimension that doesn't fit in a `usize` are rejected with
+    /// `Error::TooManyQubits`.
+    pub fn set_auto_grow(&mut self, auto_grow: bool) {
+        self.auto_grow = auto_grow;
+    }
+
+    /// Set a lower bound for the theoretical change in trace. If an operation, instrument,
+    /// or measurement pushes `trace_change` below this threshold, the simulation is aborted
+    /// and the simulator transitions into the `Error::TraceBelowThreshold` state.
+    ///
+    /// This is useful to cheaply discard low-weight trajectories in post-selection workflows.
+    /// The default threshold is 0.0, which never aborts a simulation.
+    pub fn set_min_trace_change(&mut self, threshold: f64) {
+        self.min_trace_change = threshold;
+    }
+
+    /// Set the number of state vector entries the kernel processes per chunk when applying
+    /// operations and instruments. A `chunk_size` of 0 is treated as 1.
+    ///
+    /// The default is derived from the size of the state vector and the number of available cores.
+    /// Too-small chunks spend more time on scheduling than on useful work, while too-large
+    /// chunks leave cores idle because there are fewer chunks than threads.
+    pub fn set_parallel_chunk_size(&mut self, chunk_size: usize) {
+        self.parallel_chunk_size = chunk_size.max(1);
+    }
+
+    /// If `strict_sampling` is true, `sample_instrument` returns `Error::NegativeRescaledSample`
+    /// when the random sample rescaled for the sampled outcome is below `-tolerance`, which
+    /// indicates an inconsistency in the probability accounting. Otherwise, the rescaled
+    /// sample is clamped to 0. The default is false.
+    pub fn set_strict_sampling(&mut self, strict_sampling: bool) {
+        self.strict_sampling = strict_sampling;
+    }
+
+    /// Sets a callback that is notified with a `TraceEvent` every time a Kraus operator is
+    /// sampled by `apply_operation`, `apply_instrument`, or `sample_instrument`. This allows
+    /// logging or asserting on the exact trajectory taken by the simulation.
+    pub fn set_trace_observer(&mut self, observer: Box<dyn FnMut(TraceEvent) + Send>) {
+        self.trace_observer = Some(observer);
+    }
+
+    /// Removes the callback set by `set_trace_observer`.
+    pub fn clear_trace_observer(&mut self) {
+        self.trace_observer = None;
+    }
+
+    /// Notifies the trace observer, if any, that `kraus_index` was sampled.
+    fn notify_trace_observer(
+        &mut self,
+        kraus_operators: &[SquareMatrix],
+        qubits: &[usize],
+        kraus_index: usize,
+        outcome: Option<usize>,
+    ) -> Result<(), Error> {
+        if let Some(observer) = self.trace_observer.as_mut() {
+            observer(TraceEvent {
+                kraus_operators,
+                qubits,
+                kraus_index,
+                outcome,
+                trace_change: self.state.as_ref()?.trace_change,
+            });
+        }
+        Ok(())
+    }
+
+    /// Grows the system to fit `qubits` if `auto_grow` is enabled. The ids are validated
+    /// before growing, so that invalid `qubits` leave the system untouched.
+    fn grow_to_fit(&mut self, qubits: &[usize]) -> Result<(), Error> {
+        let number_of_qubits = self.state.as_ref()?.number_of_qubits;
+        match qubits.iter().max() {
+            Some(max_id) if self.auto_grow && *max_id >= number_of_qubits => {
+                for (i, id) in qubits.iter().enumerate() {
+                    if qubits[..i].contains(id) {
+                        return Err(Error::DuplicateQubit(*id));
+                    }
+                }
+                self.add_qubits(max_id + 1 - number_of_qubits)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the `qubits` ids are in range and distinct.
+    fn check_qubits(&self, qubits: &[usize]) -> Result<(), Error> {
+        let number_of_qubits = self.state.as_ref()?.number_of_qubits;
+        for (i, id) in qubits.iter().enumerate() {
+            if *id >= number_of_qubits {
+                return Err(Error::QubitIndexOutOfRange {
+                    index: *id,
+                    number_of_qubits,
+                });
+            }
+            if qubits[..i].contains(id) {
+                return Err(Error::DuplicateQubit(*id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the `unitary` matrix to the given qubit ids. This is a fast path for
+    /// noiseless gates: unlike `apply_operation`, no Kraus operator is sampled and the
+    /// state is not renormalized, and `trace_change` stays the same.
+    ///
+    /// The caller is responsible for `unitary` being unitary. Otherwise, the state of the
+    /// simulator won't be normalized afterwards.
+    pub fn apply_unitary(&mut self, unitary: &SquareMatrix, qubits: &[usize]) -> Result<(), Error> {
+        let (nrows, ncols) = unitary.shape();
+        if nrows != ncols || ncols != 1 << qubits.len() {
+            return Err(Error::MatrixVecDimensionMismatch {
+                nrows,
+                ncols,
+                vec_dim: 1 << qubits.len(),
+            });
+        }
+        self.grow_to_fit(qubits)?;
+        self.check_qubits(qubits)?;
+        // The kernel multiplies by the transpose of the matrix it receives.
+        apply_kernel(
+            &mut self.state.as_mut()?.data,
+            &unitary.transpose(),
+            qubits,
+            self.parallel_chunk_size,
+        )
+    }
+
+    /// Applies a sequence of SWAP gates, one for each pair of qubit ids in `swaps`, in order.
+    /// Each SWAP permutes the entries of the state vector instead of applying a matrix, so
+    /// this is faster than applying SWAP operations, e.g. to route a circuit.
+    ///
+    /// All the SWAPs are validated before growing the system or permuting anything, so an
+    /// invalid SWAP leaves the state untouched. A qubit may appear in several SWAPs.
+    /// Errors: returns `Error::DuplicateQubit` if both qubits of a SWAP are the same.
+    pub fn apply_swap_network(&mut self, swaps: &[(usize, usize)]) -> Result<(), Error> {
+        if let Some((a, _)) = swaps.iter().find(|(a, b)| a == b) {
+            return Err(Error::DuplicateQubit(*a));
+        }
+        if let Some(max_id) = swaps.iter().map(|(a, b)| *a.max(b)).max() {
+            self.grow_to_fit(&[max_id])?;
+        }
+        for (a, b) in swaps {
+            self.check_qubits(&[*a, *b])?;
+        }
+        let state = self.state.as_mut()?;
+        for (a, b) in swaps {
+            state.swap_qubits(*a, *b);
+        }
+        Ok(())
+    }
+
+    /// Measures `qubit` in the orthonormal basis given by the columns of the unitary `basis`
+    /// matrix and returns the index of the observed basis vector. E.g., the Hadamard matrix
+    /// measures in the X basis.
+    ///
+    /// The qubit is rotated into the computational basis with `basis`†, measured in the Z
+    /// basis with `sample_instrument_with_distribution`, and rotated back with `basis`, so
+    /// afterwards it is in the observed basis state.
+    ///
+    /// Errors: returns `Error::InvalidState` if `basis` is not a 2 x 2 unitary matrix.
+    pub fn measure_in_basis(&mut self, qubit: usize, basis: &SquareMatrix) -> Result<usize, Error> {
+        if basis.shape() != (2, 2)
+            || (basis.adjoint() * basis - SquareMatrix::identity(2, 2)).norm() > self.tolerance
+        {
+            return Err(Error::InvalidState(
+                "measurement basis should be a 2 x 2 unitary matrix".to_string(),
+            ));
+        }
+        let projector = |outcome: usize| {
+            let mut projector = SquareMatrix::zeros(2, 2);
+            projector[(outcome, outcome)] = Complex::ONE;
+            Operation::new(vec![projector])
+        };
+        let mz = Instrument::new(vec![projector(0)?, projector(1)?])?;
+
+        self.apply_unitary(&basis.adjoint(), &[qubit])?;
+        let random_sample = self.rng.gen();
+        let outcome = self.sample_instrument_with_distribution(&mz, &[qubit], random_sample)?;
+        self.apply_unitary(basis, &[qubit])?;
+        Ok(outcome)
+    }
+
+    /// Applies `operation` to the given qubit ids, and then post-selects on the subspace
+    /// given by the orthogonal projector `subspace_projector` acting on the same qubits,
+    /// renormalizing the state. The probability of staying in the subspace is multiplied
+    /// into `trace_change`, so the leakage probability of the operation is reflected there.
+    ///
+    /// Errors: returns `Error::InvalidProjector` if `subspace_projector` is not a Hermitian
+    /// idempotent matrix with the right dimensions for the number of target `qubits`.
+    pub fn apply_operation_projected(
+        &mut self,
+        operation: &Operation,
+        qubits: &[usize],
+        subspace_projector: &SquareMatrix,
+    ) -> Result<(), Error> {
+        let dim = 1 << qubits.len();
+        if subspace_projector.shape() != (dim, dim) {
+            return Err(Error::InvalidProjector(format!(
+                "projector should have dimensions {dim} x {dim}, but it is {} x {}",
+                subspace_projector.nrows(),
+                subspace_projector.ncols()
+            )));
+        }
+        if !is_hermitian(subspace_projector, self.tolerance)
+            || (subspace_projector * subspace_projector - subspace_projector).norm()
+                > self.tolerance
+        {
+            return Err(Error::InvalidProjector(
+                "projector should satisfy P = P† = P²".to_string(),
+            ));
+        }
+
+        self.apply_operation(operation, qubits)?;
+
+        // Since P† P = P, the projector is its own effect matrix. Like all the matrices
+        // consumed by the kernel, it has to be transposed. See `Operation::new`.
+        let projector_transpose = subspace_projector.transpose();
+        let state = self.state.as_mut()?;
+        let in_subspace_probability =
+            state.effect_probability(&projector_transpose, qubits, self.parallel_chunk_size)?;
+        state.scale_trace_change(in_subspace_probability);
+        apply_kernel(
+            &mut state.data,
+            &projector_transpose,
+            qubits,
+            self.parallel_chunk_size,
+        )?;
+        if let Err(err) =
+            state.renormalize_with_norm_squared(in_subspace_probability, self.tolerance)
+        {
+            handle_error!(self, err);
+        }
+        self.shot_stats.renormalizations += 1;
+        self.check_min_trace_change()
+    }
+
+    /// Applies `operation` to the given qubit ids like `apply_operation` does. If
+    /// `renormalize` is false, the state is left sub-normalized after applying the sampled
+    /// Kraus operator, so that the caller can inspect its norm. `trace_change` is updated
+    /// in both cases.
+    ///
+    /// The next operation or instrument applied with renormalization will normalize the
+    /// state again. The norm left by skipping renormalization is divided out of its
+    /// probability, so that `trace_change` stays the product of the probabilities of the
+    /// sampled events.
+    pub fn apply_operation_raw(
+        &mut self,
+        operation: &Operation,
+        qubits: &[usize],
+        renormalize: bool,
+    ) -> Result<(), Error> {
+        self.grow_to_fit(qubits)?;
+        self.check_qubits(qubits)?;
+
+        let renormalization_factor = self.state.as_mut()?.effect_probability(
+            operation.effect_matrix(),
+            qubits,
+            self.parallel_chunk_size,
+        )?;
+        self.state
             .as_mut()?
-            .effect_probability(operation.effect_matrix(), qubits)?;
-        self.state.as_mut()?.trace_change *= renormalization_factor;
+            .scale_trace_change(renormalization_factor / self.pending_norm_squared);
 
-        if let Err(err) = self.state.as_mut()?.sample_kraus_operators(
+        let (kraus_index, evaluations) = match self.state.as_mut()?.sample_kraus_operators(
             operation.kraus_operators(),
             qubits,
             renormalization_factor,
             self.rng.gen(),
+            self.parallel_chunk_size,
+            renormalize,
+            self.tolerance,
         ) {
-            handle_error!(self, err);
+            Ok(sampled) => sampled,
+            Err(err) => {
+                handle_error!(self, err);
+            }
         };
+        self.pending_norm_squared = if renormalize {
+            1.0
+        } else {
+            self.state.as_ref()?.norm_squared()
+        };
+        self.shot_stats.kraus_samples += 1;
+        self.shot_stats.probability_evaluations += evaluations;
+        self.shot_stats.renormalizations += usize::from(renormalize);
+        self.notify_trace_observer(operation.kraus_operators(), qubits, kraus_index, None)?;
+
+        self.check_min_trace_change()
+    }
+
+    /// Applies `operation` independently to each group of qubit ids in `qubit_groups`, e.g.
+    /// to model uniform single-qubit noise across a register. This is equivalent to calling
+    /// `apply_operation` once per group, in order.
+    ///
+    /// All the groups are validated before growing the system or applying anything.
+    /// Errors: returns `Error::DuplicateQubit` if the groups are not pairwise disjoint, and
+    /// `Error::MatrixVecDimensionMismatch` if a group doesn't match the number of qubits of
+    /// `operation`.
+    pub fn apply_operation_broadcast(
+        &mut self,
+        operation: &Operation,
+        qubit_groups: &[Vec<usize>],
+    ) -> Result<(), Error> {
+        let dimension = 1 << operation.number_of_qubits();
+        if let Some(group) = qubit_groups
+            .iter()
+            .find(|group| group.len() != operation.number_of_qubits())
+        {
+            return Err(Error::MatrixVecDimensionMismatch {
+                nrows: dimension,
+                ncols: dimension,
+                vec_dim: 1 << group.len(),
+            });
+        }
+        // Check for duplicates before growing the system, so that invalid groups leave the
+        // simulator untouched even with auto-grow enabled.
+        let all_qubits = qubit_groups.concat();
+        for (i, id) in all_qubits.iter().enumerate() {
+            if all_qubits[..i].contains(id) {
+                return Err(Error::DuplicateQubit(*id));
+            }
+        }
+        self.grow_to_fit(&all_qubits)?;
+        self.check_qubits(&all_qubits)?;
+        for qubits in qubit_groups {
+            self.apply_operation_raw(operation, qubits, true)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the simulator is in a valid state. After an unrecoverable error,
+    /// the simulator stays invalid until `reset`, `soft_reset`, or `set_state` are called.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.state.is_ok()
+    }
+
+    /// Resets the quantum system to the ground state and its `trace_change` to 1.0, also
+    /// recovering the simulator if it was in an error state.
+    ///
+    /// Unlike constructing a new simulator, this preserves the configuration of the
+    /// simulator: its random number generator, which keeps advancing across trajectories,
+    /// the minimum trace change, and the parallel chunk size. Accumulated counters, such as
+    /// `shot_stats`, are kept too; `reset` also clears them.
+    pub fn soft_reset(&mut self) {
+        self.state = Ok(StateVector::new(self.dimension.ilog2() as usize));
+        self.pending_norm_squared = 1.0;
+    }
+
+    /// Reinitializes the quantum system to the ground state and clears the counters of the
+    /// simulator, recovering it if it was in an error state. Unlike `soft_reset`, nothing
+    /// accumulated along previous trajectories is kept; the configuration of the simulator
+    /// is preserved.
+    pub fn reset(&mut self) {
+        self.soft_reset();
+        self.reset_stats();
+    }
+
+    /// Returns the joint probability of observing a measurement record, without sampling
+    /// and without modifying the state of the simulator. Each step is an instrument, the
+    /// qubit ids it acts on, and the index of its observed outcome, applied in order.
+    ///
+    /// Performance note: the selected outcomes are applied one Kraus operator at a time,
+    /// so the cost grows with the product of the numbers of Kraus operators of the selected
+    /// outcomes. Projective measurements have a single Kraus operator per outcome.
+    pub fn measurement_record_probability(
+        &self,
+        steps: &[(Instrument, Vec<usize>, usize)],
+    ) -> Result<f64, Error> {
+        for (instrument, qubits, outcome) in steps {
+            self.check_qubits(qubits)?;
+            if *outcome >= instrument.num_operations() {
+                return Err(Error::InstrumentOutcomeOutOfBounds(*outcome));
+            }
+        }
+        branch_probability(
+            &self.state.as_ref()?.data,
+            steps,
+            self.parallel_chunk_size,
+            self.tolerance,
+        )
+    }
+
+    /// Returns an owned copy of the current state, which can later be restored with
+    /// `set_state`. This allows exploring several branches of a measurement from the same
+    /// starting point.
+    pub fn snapshot(&self) -> Result<StateVector, Error> {
+        Ok(self.state.as_ref()?.clone())
+    }
 
+    /// Returns the expectation values `[⟨X⟩, ⟨Y⟩, ⟨Z⟩]` of each qubit in the system,
+    /// i.e. the Bloch vector of each qubit's reduced density matrix.
+    pub fn single_qubit_pauli_expectations(&self) -> Result<Vec<[f64; 3]>, Error> {
+        Ok(self.state.as_ref()?.single_qubit_pauli_expectations())
+    }
+
+    /// Returns the `alpha`-stabilizer Rényi entropy Mₐ of the current state, a measure of its
+    /// non-stabilizerness ("magic"):
+    /// Mₐ = log(Σₚ Ξₚ^α) / (1 - α) - log(d), with Ξₚ = ⟨ψ|P|ψ⟩² / d
+    /// where P ranges over all Pauli strings and d = 2^`number_of_qubits`. For `alpha` = 1
+    /// the Shannon limit -Σₚ Ξₚ log(Ξₚ) - log(d) is returned. Stabilizer states yield 0.
+    ///
+    /// Performance note: this method evaluates the 4^n Pauli strings in O(8^n) time, so it
+    /// is only practical for systems of up to about 10 qubits.
+    pub fn stabilizer_renyi_entropy(&self, alpha: f64) -> Result<f64, Error> {
+        if !alpha.is_finite() || alpha < 0.0 {
+            return Err(Error::InvalidRenyiOrder(alpha));
+        }
+        Ok(self
+            .state
+            .as_ref()?
+            .stabilizer_renyi_entropy(alpha, self.tolerance))
+    }
+
+    /// Returns the overlap `⟨ψ₀ ⊗ ψ₁ ⊗ … | current⟩` between the current state and the
+    /// product state given by one normalized 2-vector `[⟨0|ψᵢ⟩, ⟨1|ψᵢ⟩]` per qubit.
+    pub fn product_state_overlap(
+        &self,
+        single_qubit_states: &[[Complex<f64>; 2]],
+    ) -> Result<Complex<f64>, Error> {
+        self.state
+            .as_ref()?
+            .product_state_overlap(single_qubit_states, self.tolerance)
+    }
+
+    /// Returns the coherent information of `operation` acting on `qubits` for the current
+    /// state. See `StateVector::coherent_information`.
+    pub fn coherent_information(
+        &self,
+        operation: &Operation,
+        qubits: &[usize],
+    ) -> Result<f64, Error> {
+        self.state
+            .as_ref()?
+            .coherent_information_with_tolerance(operation, qubits, self.tolerance)
+    }
+
+    /// Returns the exact variance `⟨O²⟩ - ⟨O⟩²` of the Hermitian `observable` acting on
+    /// `qubits` in the current state. This is the intrinsic quantum variance of the
+    /// observable, as opposed to the shot noise of an estimate obtained by sampling.
+    pub fn expectation_variance(
+        &self,
+        observable: &SquareMatrix,
+        qubits: &[usize],
+    ) -> Result<f64, Error> {
+        self.check_qubits(qubits)?;
+        if !is_hermitian(observable, self.tolerance) {
+            return Err(Error::NonHermitianObservable);
+        }
+        self.state
+            .as_ref()?
+            .expectation_variance(observable, qubits, self.parallel_chunk_size)
+    }
+
+    /// Returns the probability of each outcome of `instrument` acting on `qubits` in the
+    /// current state, without sampling an outcome or modifying the state.
+    pub fn instrument_probabilities(
+        &self,
+        instrument: &Instrument,
+        qubits: &[usize],
+    ) -> Result<Vec<f64>, Error> {
+        self.check_qubits(qubits)?;
+        let state = self.state.as_ref()?;
+        let renormalization_factor = state.effect_probability(
+            instrument.total_effect(),
+            qubits,
+            self.parallel_chunk_size,
+        )?;
+        if renormalization_factor < self.tolerance {
+            return Err(Error::ProbabilityZeroEvent);
+        }
+        (0..instrument.num_operations())
+            .map(|outcome| {
+                Ok(state.effect_probability(
+                    instrument.operation(outcome).effect_matrix(),
+                    qubits,
+                    self.parallel_chunk_size,
+                )? / renormalization_factor)
+            })
+            .collect()
+    }
+
+    /// Samples `instrument` acting on `qubits` `shots` times, each time from the current
+    /// state, and returns a histogram with the number of times each outcome was observed.
+    ///
+    /// Each shot performs the selective evolution on its own copy of the state, which is
+    /// discarded afterwards, so the state of the simulator is the same before and after
+    /// this call. Only the random number generator advances.
+    pub fn sample_instrument_shots(
+        &mut self,
+        instrument: &Instrument,
+        qubits: &[usize],
+        shots: usize,
+    ) -> Result<Vec<usize>, Error> {
+        self.check_qubits(qubits)?;
+        let initial_state = self.state.as_ref()?.clone();
+        let initial_pending_norm_squared = self.pending_norm_squared;
+        let mut histogram = vec![0; instrument.num_operations()];
+        for _ in 0..shots {
+            let outcome = self.sample_instrument(instrument, qubits);
+            self.state = Ok(initial_state.clone());
+            self.pending_norm_squared = initial_pending_norm_squared;
+            histogram[outcome?] += 1;
+        }
+        Ok(histogram)
+    }
+
+    /// Returns the natural logarithm of `trace_change`, which doesn't underflow over long
+    /// circuits. See `StateVector::log_trace_change`.
+    pub fn log_trace_change(&self) -> Result<f64, Error> {
+        Ok(self.state.as_ref()?.log_trace_change())
+    }
+
+    /// Returns the work done by the simulator since it was created or its stats were last
+    /// cleared with `reset_stats`. Like the rest of the accumulated counters, the stats are
+    /// preserved by `soft_reset`.
+    #[must_use]
+    pub fn shot_stats(&self) -> ShotStats {
+        self.shot_stats
+    }
+
+    /// Clears the work recorded in `shot_stats`.
+    pub fn reset_stats(&mut self) {
+        self.shot_stats = ShotStats::default();
+    }
+
+    /// Like `sample_instrument_shots`, but returns the outcome of each shot together with
+    /// the work done to sample it. This allows correlating the cost of a shot with its
+    /// outcome, e.g. through `ShotStats::probability_evaluations`. The `shot_stats` of the
+    /// simulator are the same before and after this call.
+    pub fn sample_instrument_shots_with_stats(
+        &mut self,
+        instrument: &Instrument,
+        qubits: &[usize],
+        shots: usize,
+    ) -> Result<Vec<(usize, ShotStats)>, Error> {
+        self.check_qubits(qubits)?;
+        let initial_state = self.state.as_ref()?.clone();
+        let initial_pending_norm_squared = self.pending_norm_squared;
+        let initial_stats = self.shot_stats;
+        let mut results = Vec::with_capacity(shots);
+        for _ in 0..shots {
+            self.shot_stats = ShotStats::default();
+            let outcome = self.sample_instrument(instrument, qubits);
+            let stats = self.shot_stats;
+            self.state = Ok(initial_state.clone());
+            self.pending_norm_squared = initial_pending_norm_squared;
+            self.shot_stats = initial_stats;
+            results.push((outcome?, stats));
+        }
+        Ok(results)
+    }
+
+    /// Runs the parameterized circuit applied by `circuit` once for each parameter set in
+    /// `parameter_sets`, each time starting from a copy of the current state, and returns
+    /// the resulting states in the same order as `parameter_sets`.
+    ///
+    /// Each run uses a new simulator with the same configuration as this one, whose random
+    /// number generator is seeded from this simulator's generator, so a batch is reproducible
+    /// whenever this simulator is. The state of this simulator is not modified.
+    pub fn run_parameterized_batch(
+        &mut self,
+        circuit: impl Fn(&mut Self, &[f64]) -> Result<(), Error>,
+        parameter_sets: &[Vec<f64>],
+    ) -> Result<Vec<StateVector>, Error> {
+        self.state.as_ref()?;
+        let mut final_states = Vec::with_capacity(parameter_sets.len());
+        for parameters in parameter_sets {
+            let mut sim = self.fork();
+            sim.reset_stats();
+            circuit(&mut sim, parameters)?;
+            final_states.push(sim.state?);
+        }
+        Ok(final_states)
+    }
+
+    /// Returns the derivative of the expectation value of the Hermitian `observable` acting
+    /// on `qubits` with respect to `parameters[parameter_index]`, computed with the
+    /// parameter-shift rule:
+    /// ∂⟨O⟩/∂θ = (⟨O⟩(θ + π/2) - ⟨O⟩(θ - π/2)) / 2
+    ///
+    /// The circuit is applied by `circuit` to copies of the current state, as in
+    /// `run_parameterized_batch`. The rule is exact when the parameter only enters the
+    /// circuit through a single gate of the form exp(-iθP/2), where P is a Pauli operator,
+    /// as in the rotation gates.
+    pub fn parameter_shift_gradient(
+        &mut self,
+        circuit: impl Fn(&mut Self, &[f64]) -> Result<(), Error>,
+        parameters: &[f64],
+        observable: &SquareMatrix,
+        qubits: &[usize],
+        parameter_index: usize,
+    ) -> Result<f64, Error> {
+        self.check_qubits(qubits)?;
+        if !is_hermitian(observable, self.tolerance) {
+            return Err(Error::NonHermitianObservable);
+        }
+        if parameter_index >= parameters.len() {
+            return Err(Error::ParameterIndexOutOfBounds(parameter_index));
+        }
+
+        let mut shifted_parameters = vec![parameters.to_vec(), parameters.to_vec()];
+        shifted_parameters[0][parameter_index] += std::f64::consts::FRAC_PI_2;
+        shifted_parameters[1][parameter_index] -= std::f64::consts::FRAC_PI_2;
+        let shifted_states = self.run_parameterized_batch(circuit, &shifted_parameters)?;
+        let forward = shifted_states[0].expectation_value(observable, qubits)?;
+        let backward = shifted_states[1].expectation_value(observable, qubits)?;
+        Ok((forward - backward) / 2.0)
+    }
+
+    /// Aborts the simulation if `trace_change` dropped below `min_trace_change`.
+    fn check_min_trace_change(&mut self) -> Result<(), Error> {
+        let trace_change = self.state.as_ref()?.trace_change;
+        if trace_change < self.min_trace_change {
+            let err = Error::TraceBelowThreshold(trace_change);
+            handle_error!(self, err);
+        }
         Ok(())
     }
+}
+
+/// The clone copies the state, the configuration, and the random number generator of the
+/// original, so both replay the same samples. Use `StateVectorSimulator::fork` to follow an
+/// independent trajectory instead. The trace observer is not cloned.
+impl Clone for StateVectorSimulator {
+    fn clone(&self) -> Self {
+        self.copy_with_rng(self.rng.clone())
+    }
+}
+
+impl NoisySimulator for StateVectorSimulator {
+    type State = StateVector;
+
+    /// Creates a new `StateVectorSimulator`.
+    fn new(number_of_qubits: usize) -> Self {
+        Self::new_with_rng(number_of_qubits, StdRng::from_entropy())
+    }
+
+    /// Creates a new `StateVectorSimulator` with a given seed for its random number generator.
+    fn new_with_seed(number_of_qubits: usize, seed: u64) -> Self {
+        Self::new_with_rng(number_of_qubits, StdRng::seed_from_u64(seed))
+    }
+
+    /// Apply an operation to given qubit ids.
+    fn apply_operation(&mut self, operation: &Operation, qubits: &[usize]) -> Result<(), Error> {
+        self.apply_operation_raw(operation, qubits, true)
+    }
 
     /// Apply non selective evolution.
     fn apply_instrument(&mut self, instrument: &Instrument, qubits: &[usize]) -> Result<(), Error> {
-        self.check_out_of_bounds_qubits(qubits)?;
+        self.grow_to_fit(qubits)?;
+        self.check_qubits(qubits)?;
 
-        let renormalization_factor = self
-            .state
+        let renormalization_factor = self.state.as_mut()?.effect_probability(
+            instrument.total_effect(),
+            qubits,
+            self.parallel_chunk_size,
+        )?;
+        self.state
             .as_mut()?
-            .effect_probability(instrument.total_effect(), qubits)?;
-        self.state.as_mut()?.trace_change *= renormalization_factor;
+            .scale_trace_change(renormalization_factor / self.pending_norm_squared);
 
-        if let Err(err) = self.state.as_mut()?.sample_kraus_operators(
+        let (kraus_index, evaluations) = match self.state.as_mut()?.sample_kraus_operators(
             instrument.non_selective_kraus_operators(),
             qubits,
             renormalization_factor,
             self.rng.gen(),
+            self.parallel_chunk_size,
+            true,
+            self.tolerance,
         ) {
-            handle_error!(self, err);
+            Ok(sampled) => sampled,
+            Err(err) => {
+                handle_error!(self, err);
+            }
         };
+        self.pending_norm_squared = 1.0;
+        self.shot_stats.kraus_samples += 1;
+        self.shot_stats.probability_evaluations += evaluations;
+        self.shot_stats.renormalizations += 1;
+        self.notify_trace_observer(
+            instrument.non_selective_kraus_operators(),
+            qubits,
+            kraus_index,
+            None,
+        )?;
 
-        Ok(())
+        self.check_min_trace_change()
     }
 
     /// Performs selective evolution under the given instrument.
@@ -292,23 +1603,27 @@ impl NoisySimulator for StateVectorSimulator {
         qubits: &[usize],
         random_sample: f64,
     ) -> Result<usize, Error> {
-        self.check_out_of_bounds_qubits(qubits)?;
+        self.grow_to_fit(qubits)?;
+        self.check_qubits(qubits)?;
 
-        let renormalization_factor = self
-            .state
-            .as_mut()?
-            .effect_probability(instrument.total_effect(), qubits)?;
+        let renormalization_factor = self.state.as_mut()?.effect_probability(
+            instrument.total_effect(),
+            qubits,
+            self.parallel_chunk_size,
+        )?;
         let mut last_non_zero_norm_squared = 0.0;
         let mut summed_probability = 0.0;
         let mut last_non_zero_outcome = 0;
 
         for outcome in 0..instrument.num_operations() {
-            let norm_squared = self
-                .state
-                .as_mut()?
-                .effect_probability(instrument.operation(outcome).effect_matrix(), qubits)?;
+            self.shot_stats.probability_evaluations += 1;
+            let norm_squared = self.state.as_mut()?.effect_probability(
+                instrument.operation(outcome).effect_matrix(),
+                qubits,
+                self.parallel_chunk_size,
+            )?;
             let p = norm_squared / renormalization_factor;
-            if p >= TOLERANCE {
+            if p >= self.tolerance {
                 last_non_zero_outcome = outcome;
                 last_non_zero_norm_squared = norm_squared;
             }
@@ -318,28 +1633,52 @@ impl NoisySimulator for StateVectorSimulator {
             }
         }
 
-        if summed_probability + TOLERANCE <= random_sample || last_non_zero_norm_squared < TOLERANCE
+        if summed_probability + self.tolerance <= random_sample
+            || last_non_zero_norm_squared < self.tolerance
         {
             let err = Error::FailedToSampleInstrumentOutcome;
             handle_error!(self, err);
         }
 
-        self.state.as_mut()?.trace_change *= last_non_zero_norm_squared;
-        let rescaled_random_sample = ((summed_probability - random_sample)
+        let rescaled_random_sample = (summed_probability - random_sample)
             / last_non_zero_norm_squared
-            * renormalization_factor)
-            .max(0.0);
+            * renormalization_factor;
+        if self.strict_sampling && rescaled_random_sample < -self.tolerance {
+            return Err(Error::NegativeRescaledSample(rescaled_random_sample));
+        }
+        let rescaled_random_sample = rescaled_random_sample.max(0.0);
+        self.state
+            .as_mut()?
+            .scale_trace_change(last_non_zero_norm_squared / self.pending_norm_squared);
 
-        if let Err(err) = self.state.as_mut()?.sample_kraus_operators(
-            instrument
-                .operation(last_non_zero_outcome)
-                .kraus_operators(),
+        let kraus_operators = instrument
+            .operation(last_non_zero_outcome)
+            .kraus_operators();
+        let (kraus_index, evaluations) = match self.state.as_mut()?.sample_kraus_operators(
+            kraus_operators,
             qubits,
             last_non_zero_norm_squared,
             rescaled_random_sample,
+            self.parallel_chunk_size,
+            true,
+            self.tolerance,
         ) {
-            handle_error!(self, err);
+            Ok(sampled) => sampled,
+            Err(err) => {
+                handle_error!(self, err);
+            }
         };
+        self.pending_norm_squared = 1.0;
+        self.shot_stats.kraus_samples += 1;
+        self.shot_stats.probability_evaluations += evaluations;
+        self.shot_stats.renormalizations += 1;
+        self.notify_trace_observer(
+            kraus_operators,
+            qubits,
+            kraus_index,
+            Some(last_non_zero_outcome),
+        )?;
+        self.check_min_trace_change()?;
         Ok(last_non_zero_outcome)
     }
 
@@ -357,13 +1696,14 @@ impl NoisySimulator for StateVectorSimulator {
                 new_state.dimension(),
             )));
         }
-        if !new_state.is_normalized() {
+        if !new_state.is_normalized(self.tolerance) {
             return Err(Error::InvalidState(format!(
                 "`state` is not normalized, norm_squared is {}",
                 new_state.norm_squared()
             )));
         }
         self.state = Ok(new_state);
+        self.pending_norm_squared = 1.0;
         Ok(())
     }
 
@@ -376,10 +1716,12 @@ impl NoisySimulator for StateVectorSimulator {
 
     /// Set the trace of the quantum system.
     fn set_trace(&mut self, trace: f64) -> Result<(), Error> {
-        if trace < TOLERANCE || (trace - 1.) > TOLERANCE {
+        if trace < self.tolerance || (trace - 1.) > self.tolerance {
             return Err(Error::NotNormalized(trace));
         }
-        self.state.as_mut()?.trace_change = trace;
+        let state = self.state.as_mut()?;
+        state.trace_change = trace;
+        state.log_trace_change = trace.ln();
         Ok(())
     }
 }
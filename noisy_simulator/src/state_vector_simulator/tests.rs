@@ -1,8 +1,20 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
-use super::StateVectorSimulator;
-use crate::tests::{noiseless_tests, noisy_tests};
+use super::{ShotStats, StateVector, StateVectorSimulator};
+use crate::{
+    operation::{operation, Operation},
+    tests::{
+        assert_approx_eq,
+        noiseless_tests::{self, noiseless_cnot, noiseless_h, noiseless_mz},
+        noisy_tests,
+    },
+    Error, NoisySimulator, SquareMatrix, TOLERANCE,
+};
+use nalgebra::{dmatrix, dvector};
+use num_complex::Complex;
+use rand::{rngs::StdRng, SeedableRng};
+use std::f64::consts::FRAC_1_SQRT_2;
 
 #[test]
 fn check_measuring_plus_state_yields_zero_with_50_percent_probability() {
@@ -78,9 +90,1729 @@ fn check_alternating_mz_and_mx_yield_right_probabilities() {
     );
 }
 
+#[test]
+fn check_trace_change_below_threshold_aborts_simulation() {
+    noiseless_tests::check_trace_change_below_threshold_aborts_simulation::<StateVectorSimulator>();
+}
+
+#[test]
+fn check_apply_operation_mask_targets_qubits_in_ascending_order() {
+    noiseless_tests::check_apply_operation_mask_targets_qubits_in_ascending_order::<
+        StateVectorSimulator,
+    >();
+}
+
+#[test]
+fn check_apply_operation_mask_rejects_invalid_masks() {
+    noiseless_tests::check_apply_operation_mask_rejects_invalid_masks::<StateVectorSimulator>();
+}
+
+#[test]
+fn check_apply_operation_rejects_invalid_qubit_indices() {
+    noiseless_tests::check_apply_operation_rejects_invalid_qubit_indices::<StateVectorSimulator>();
+}
+
+#[test]
+fn check_soft_reset_restores_ground_state_and_keeps_configuration() {
+    noiseless_tests::check_soft_reset_restores_ground_state_and_keeps_configuration::<
+        StateVectorSimulator,
+    >();
+}
+
 #[test]
 fn check_noisy_identity_yields_same_qubit_with_right_probability() {
     noisy_tests::check_noisy_identity_yields_same_qubit_with_right_probability::<
         StateVectorSimulator,
     >();
 }
+
+/// Assert that two Bloch vectors are equal up to a `TOLERANCE`.
+fn assert_bloch_vector_approx_eq(left: [f64; 3], right: [f64; 3]) {
+    for (l, r) in left.iter().zip(right.iter()) {
+        assert_approx_eq(*l, *r);
+    }
+}
+
+#[test]
+fn check_single_qubit_pauli_expectations_of_product_state() {
+    let h = noiseless_h();
+    let s = operation!([1., 0.;
+                        0., Complex::I;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(3);
+
+    // Prepare |0⟩ ⊗ |+⟩ ⊗ |+i⟩, in little-endian order.
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    sim.apply_operation(&h, &[2])
+        .expect("operation should succeed");
+    sim.apply_operation(&s, &[2])
+        .expect("operation should succeed");
+
+    let expectations = sim
+        .single_qubit_pauli_expectations()
+        .expect("state should be valid");
+    assert_eq!(expectations.len(), 3);
+    assert_bloch_vector_approx_eq([0., 0., 1.], expectations[0]);
+    assert_bloch_vector_approx_eq([1., 0., 0.], expectations[1]);
+    assert_bloch_vector_approx_eq([0., 1., 0.], expectations[2]);
+}
+
+#[test]
+fn check_single_qubit_pauli_expectations_of_bell_pair_vanish() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+
+    // The reduced states of a Bell pair are maximally mixed.
+    for expectation in sim
+        .single_qubit_pauli_expectations()
+        .expect("state should be valid")
+    {
+        assert_bloch_vector_approx_eq([0., 0., 0.], expectation);
+    }
+}
+
+#[test]
+fn check_product_state_overlap_with_prepared_product_state() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+
+    let zero = [Complex::new(1., 0.), Complex::new(0., 0.)];
+    let plus = [
+        Complex::new(FRAC_1_SQRT_2, 0.),
+        Complex::new(FRAC_1_SQRT_2, 0.),
+    ];
+    let minus = [
+        Complex::new(FRAC_1_SQRT_2, 0.),
+        Complex::new(-FRAC_1_SQRT_2, 0.),
+    ];
+
+    let overlap = sim
+        .product_state_overlap(&[zero, plus])
+        .expect("overlap should be computed");
+    assert_approx_eq(1., overlap.re);
+    assert_approx_eq(0., overlap.im);
+
+    let overlap = sim
+        .product_state_overlap(&[zero, minus])
+        .expect("overlap should be computed");
+    assert_approx_eq(0., overlap.norm());
+}
+
+#[test]
+fn check_product_state_overlap_with_bell_pair() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+
+    let zero = [Complex::new(1., 0.), Complex::new(0., 0.)];
+    let overlap = sim
+        .product_state_overlap(&[zero, zero])
+        .expect("overlap should be computed");
+    assert_approx_eq(FRAC_1_SQRT_2, overlap.re);
+    assert_approx_eq(0., overlap.im);
+}
+
+#[test]
+fn check_product_state_overlap_fails_on_wrong_number_of_states() {
+    let sim = StateVectorSimulator::new(2);
+    let zero = [Complex::new(1., 0.), Complex::new(0., 0.)];
+    assert!(matches!(
+        sim.product_state_overlap(&[zero]),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn check_product_state_overlap_fails_on_unnormalized_state() {
+    let sim = StateVectorSimulator::new(1);
+    let unnormalized = [Complex::new(1., 0.), Complex::new(1., 0.)];
+    assert!(matches!(
+        sim.product_state_overlap(&[unnormalized]),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn check_product_state_overlap_uses_simulator_tolerance() {
+    let slightly_unnormalized = [Complex::new(1. + 1e-9, 0.), Complex::new(0., 0.)];
+    let sim = StateVectorSimulator::new(1);
+    assert!(matches!(
+        sim.product_state_overlap(&[slightly_unnormalized]),
+        Err(Error::InvalidState(_))
+    ));
+
+    let sim = StateVectorSimulator::new(1).with_tolerance(1e-6);
+    let overlap = sim
+        .product_state_overlap(&[slightly_unnormalized])
+        .expect("overlap should be computed");
+    assert!((overlap.re - 1.).abs() < 1e-6);
+}
+
+#[test]
+fn check_parallel_chunk_size_does_not_change_final_state() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut default_sim = StateVectorSimulator::new(3);
+    let mut chunked_sim = StateVectorSimulator::new(3);
+    chunked_sim.set_parallel_chunk_size(1);
+    for sim in [&mut default_sim, &mut chunked_sim] {
+        sim.apply_operation(&h, &[0])
+            .expect("operation should succeed");
+        sim.apply_operation(&cnot, &[2, 0])
+            .expect("operation should succeed");
+    }
+
+    let default_state = default_sim.state().expect("state should be valid").data();
+    let chunked_state = chunked_sim.state().expect("state should be valid").data();
+    for (l, r) in default_state.iter().zip(chunked_state.iter()) {
+        assert_approx_eq(l.re, r.re);
+        assert_approx_eq(l.im, r.im);
+    }
+}
+
+#[test]
+fn check_parallel_chunks_match_single_chunk_bit_for_bit() {
+    // 13 qubits is above the threshold at which the kernel runs in parallel.
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let t = operation!([1., 0.;
+                        0., Complex::from_polar(1., std::f64::consts::FRAC_PI_4);])
+    .expect("operation should be valid");
+    let mut single_chunk_sim = StateVectorSimulator::new(13);
+    let mut chunked_sim = StateVectorSimulator::new(13);
+    single_chunk_sim.set_parallel_chunk_size(1 << 13);
+    chunked_sim.set_parallel_chunk_size(1 << 6);
+    for sim in [&mut single_chunk_sim, &mut chunked_sim] {
+        for qubit in 0..13 {
+            sim.apply_operation(&h, &[qubit])
+                .expect("operation should succeed");
+            sim.apply_operation(&t, &[qubit])
+                .expect("operation should succeed");
+        }
+        for qubit in 1..13 {
+            sim.apply_operation(&cnot, &[qubit, qubit - 1])
+                .expect("operation should succeed");
+        }
+    }
+
+    let single_chunk_state = single_chunk_sim
+        .state()
+        .expect("state should be valid")
+        .data();
+    let chunked_state = chunked_sim.state().expect("state should be valid").data();
+    for (l, r) in single_chunk_state.iter().zip(chunked_state.iter()) {
+        assert_eq!(l.re.to_bits(), r.re.to_bits());
+        assert_eq!(l.im.to_bits(), r.im.to_bits());
+    }
+}
+
+#[test]
+fn check_expectation_variance_of_pauli_observables() {
+    let h = noiseless_h();
+    let z: SquareMatrix = dmatrix![1., 0.; 0., -1.].map(Complex::from);
+    let x: SquareMatrix = dmatrix![0., 1.; 1., 0.].map(Complex::from);
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+
+    // Qubit 0 is in |0⟩, an eigenstate of Z.
+    let variance = sim
+        .expectation_variance(&z, &[0])
+        .expect("variance should be computed");
+    assert_approx_eq(0., variance);
+
+    // Qubit 1 is in |+⟩, an eigenstate of X with maximal Z variance.
+    let variance = sim
+        .expectation_variance(&z, &[1])
+        .expect("variance should be computed");
+    assert_approx_eq(1., variance);
+    let variance = sim
+        .expectation_variance(&x, &[1])
+        .expect("variance should be computed");
+    assert_approx_eq(0., variance);
+}
+
+#[test]
+fn check_expectation_variance_fails_on_non_hermitian_observable() {
+    let sim = StateVectorSimulator::new(1);
+    let non_hermitian: SquareMatrix = dmatrix![0., 1.; 0., 0.].map(Complex::from);
+    assert_eq!(
+        sim.expectation_variance(&non_hermitian, &[0]),
+        Err(Error::NonHermitianObservable)
+    );
+}
+
+#[test]
+fn check_simulators_with_same_rng_yield_same_trajectories() {
+    let (h, cnot, mz) = (noiseless_h(), noiseless_cnot(), noiseless_mz());
+    let mut sim1 = StateVectorSimulator::new_with_rng(2, StdRng::seed_from_u64(42));
+    let mut sim2 = StateVectorSimulator::new_with_rng(2, StdRng::seed_from_u64(42));
+
+    let mut outcomes = [Vec::new(), Vec::new()];
+    for _ in 0..300 {
+        for (sim, outcomes) in [&mut sim1, &mut sim2].into_iter().zip(outcomes.iter_mut()) {
+            sim.soft_reset();
+            sim.apply_operation(&h, &[0])
+                .expect("operation should succeed");
+            sim.apply_operation(&cnot, &[1, 0])
+                .expect("operation should succeed");
+            outcomes.push(
+                sim.sample_instrument(&mz, &[0])
+                    .expect("measurement should succeed"),
+            );
+        }
+    }
+
+    assert_eq!(outcomes[0], outcomes[1]);
+    // Both outcomes should have been observed.
+    assert!(outcomes[0].contains(&0) && outcomes[0].contains(&1));
+}
+
+#[test]
+fn check_expectation_values_of_bell_pair() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let z: SquareMatrix = dmatrix![1., 0.; 0., -1.].map(Complex::from);
+    let x: SquareMatrix = dmatrix![0., 1.; 1., 0.].map(Complex::from);
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+
+    assert_approx_eq(
+        0.,
+        state
+            .expectation_value(&z, &[0])
+            .expect("expectation value should be computed"),
+    );
+    let values = state
+        .expectation_values(&[
+            (z.kronecker(&z), vec![0, 1]),
+            (x.kronecker(&x), vec![0, 1]),
+            (x, vec![1]),
+        ])
+        .expect("expectation values should be computed");
+    assert_eq!(values.len(), 3);
+    assert_approx_eq(1., values[0]);
+    assert_approx_eq(1., values[1]);
+    assert_approx_eq(0., values[2]);
+}
+
+#[test]
+fn check_expectation_value_fails_on_observable_dimension_mismatch() {
+    let sim = StateVectorSimulator::new(2);
+    let z: SquareMatrix = dmatrix![1., 0.; 0., -1.].map(Complex::from);
+    let state = sim.state().expect("state should be valid");
+    assert!(matches!(
+        state.expectation_value(&z, &[0, 1]),
+        Err(Error::InvalidState(_))
+    ));
+    assert_eq!(
+        state.expectation_value(&z, &[2]),
+        Err(Error::QubitIndexOutOfRange {
+            index: 2,
+            number_of_qubits: 2,
+        })
+    );
+}
+
+#[test]
+fn check_stabilizer_renyi_entropy_of_stabilizer_state_is_zero() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+
+    for alpha in [0.5, 1., 2.] {
+        let entropy = sim
+            .stabilizer_renyi_entropy(alpha)
+            .expect("entropy should be computed");
+        assert_approx_eq(0., entropy);
+    }
+}
+
+#[test]
+fn check_stabilizer_renyi_entropy_of_t_state() {
+    let h = noiseless_h();
+    let t = operation!([1., 0.;
+                        0., Complex::from_polar(1., std::f64::consts::FRAC_PI_4);])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&t, &[0])
+        .expect("operation should succeed");
+
+    // The Pauli distribution of the T state is Ξ = [1/2, 1/4, 1/4, 0].
+    let entropy = sim
+        .stabilizer_renyi_entropy(2.)
+        .expect("entropy should be computed");
+    assert_approx_eq((4_f64 / 3.).ln(), entropy);
+}
+
+#[test]
+fn check_stabilizer_renyi_entropy_fails_on_negative_order() {
+    let sim = StateVectorSimulator::new(1);
+    assert_eq!(
+        sim.stabilizer_renyi_entropy(-1.),
+        Err(Error::InvalidRenyiOrder(-1.))
+    );
+}
+
+/// Assert that two matrices are equal up to a `TOLERANCE`.
+fn assert_matrix_approx_eq(left: &SquareMatrix, right: &SquareMatrix) {
+    assert_eq!(left.shape(), right.shape());
+    for (l, r) in left.iter().zip(right.iter()) {
+        assert_approx_eq(0., (l - r).norm());
+    }
+}
+
+#[test]
+fn check_reduced_density_matrix_of_bell_pair_is_maximally_mixed() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+
+    let maximally_mixed: SquareMatrix = dmatrix![0.5, 0.; 0., 0.5].map(Complex::from);
+    for qubit in 0..2 {
+        let reduced = state
+            .reduced_density_matrix(&[qubit])
+            .expect("reduced density matrix should be computed");
+        assert_matrix_approx_eq(&maximally_mixed, &reduced);
+    }
+}
+
+#[test]
+fn check_reduced_density_matrix_of_non_contiguous_qubits() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(3);
+    // Entangle qubits 0 and 2, leaving qubit 1 in |0⟩.
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[2, 0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+
+    let reduced = state
+        .reduced_density_matrix(&[0, 2])
+        .expect("reduced density matrix should be computed");
+    let bell_pair: SquareMatrix = dmatrix![
+        0.5, 0., 0., 0.5;
+        0.,  0., 0., 0.;
+        0.,  0., 0., 0.;
+        0.5, 0., 0., 0.5;
+    ]
+    .map(Complex::from);
+    assert_matrix_approx_eq(&bell_pair, &reduced);
+}
+
+#[test]
+fn check_reduced_density_matrix_follows_qubits_order() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new(2);
+    // Prepare |+⟩ ⊗ |0⟩, in little-endian order.
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+
+    let reduced = state
+        .reduced_density_matrix(&[0, 1])
+        .expect("reduced density matrix should be computed");
+    let plus_zero: SquareMatrix = dmatrix![
+        0.5, 0.5, 0., 0.;
+        0.5, 0.5, 0., 0.;
+        0.,  0.,  0., 0.;
+        0.,  0.,  0., 0.;
+    ]
+    .map(Complex::from);
+    assert_matrix_approx_eq(&plus_zero, &reduced);
+
+    let reduced = state
+        .reduced_density_matrix(&[1, 0])
+        .expect("reduced density matrix should be computed");
+    let zero_plus: SquareMatrix = dmatrix![
+        0.5, 0., 0.5, 0.;
+        0.,  0., 0.,  0.;
+        0.5, 0., 0.5, 0.;
+        0.,  0., 0.,  0.;
+    ]
+    .map(Complex::from);
+    assert_matrix_approx_eq(&zero_plus, &reduced);
+
+    assert_eq!(
+        state.reduced_density_matrix(&[2]),
+        Err(Error::QubitIndexOutOfRange {
+            index: 2,
+            number_of_qubits: 2,
+        })
+    );
+}
+
+#[test]
+fn check_auto_grow_expands_register_on_out_of_range_qubit() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new(1);
+    assert_eq!(
+        sim.apply_operation(&h, &[2]),
+        Err(Error::QubitIndexOutOfRange {
+            index: 2,
+            number_of_qubits: 1,
+        })
+    );
+
+    sim.set_auto_grow(true);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&h, &[2])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+    assert_eq!(3, state.number_of_qubits());
+    assert_eq!(8, state.dimension());
+
+    // The state is |+⟩ ⊗ |0⟩ ⊗ |+⟩ in little-endian order.
+    let expected = [0.5, 0.5, 0., 0., 0.5, 0.5, 0., 0.];
+    for (l, r) in expected.iter().zip(state.data().iter()) {
+        assert_approx_eq(*l, r.re);
+        assert_approx_eq(0., r.im);
+    }
+}
+
+#[test]
+fn check_add_qubits_preserves_amplitudes() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.add_qubits(1).expect("qubits should be added");
+    let state = sim.state().expect("state should be valid");
+    let expected = [FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0., 0.];
+    for (l, r) in expected.iter().zip(state.data().iter()) {
+        assert_approx_eq(*l, r.re);
+    }
+}
+
+#[test]
+fn check_auto_grow_rejects_invalid_qubits_without_growing() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(1);
+    sim.set_auto_grow(true);
+    assert_eq!(
+        sim.apply_operation(&h, &[usize::BITS as usize]),
+        Err(Error::TooManyQubits(usize::BITS as usize + 1))
+    );
+    assert_eq!(
+        sim.apply_operation(&cnot, &[3, 3]),
+        Err(Error::DuplicateQubit(3))
+    );
+    assert_eq!(
+        sim.add_qubits(usize::MAX),
+        Err(Error::TooManyQubits(usize::MAX))
+    );
+    let state = sim.state().expect("state should be valid");
+    assert_eq!(1, state.number_of_qubits());
+    assert_eq!(2, state.dimension());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn check_state_vector_json_round_trip_is_bit_identical() {
+    let (h, t) = (
+        noiseless_h(),
+        operation!([1., 0.;
+                    0., Complex::from_polar(1., std::f64::consts::FRAC_PI_4);])
+        .expect("operation should be valid"),
+    );
+    let mut sim = StateVectorSimulator::new_with_seed(2, 42);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&t, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+
+    let json = serde_json::to_string(state).expect("state should be serialized");
+    let deserialized: super::StateVector =
+        serde_json::from_str(&json).expect("state should be deserialized");
+    assert_eq!(state.dimension(), deserialized.dimension());
+    assert_eq!(state.number_of_qubits(), deserialized.number_of_qubits());
+    assert_eq!(
+        state.trace_change().to_bits(),
+        deserialized.trace_change().to_bits()
+    );
+    for (l, r) in state.data().iter().zip(deserialized.data().iter()) {
+        assert_eq!(l.re.to_bits(), r.re.to_bits());
+        assert_eq!(l.im.to_bits(), r.im.to_bits());
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn check_state_vector_deserialization_rejects_inconsistent_dimensions() {
+    let json =
+        r#"{"dimension":4,"number_of_qubits":1,"trace_change":1.0,"data":[[1.0,0.0],[0.0,0.0]]}"#;
+    assert!(serde_json::from_str::<super::StateVector>(json).is_err());
+    let json = r#"{"dimension":2,"number_of_qubits":1,"trace_change":1.0,"data":[[1.0,0.0]]}"#;
+    assert!(serde_json::from_str::<super::StateVector>(json).is_err());
+}
+
+#[test]
+fn check_coherent_information_of_identity_channel_on_bell_pair_is_one() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let identity = operation!([1., 0.;
+                               0., 1.;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    let coherent_information = sim
+        .coherent_information(&identity, &[0])
+        .expect("coherent information should be computed");
+    assert_approx_eq(1., coherent_information);
+}
+
+#[test]
+fn check_coherent_information_of_dephasing_channel_on_bell_pair_is_zero() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let dephasing = operation!([1., 0.;
+                                0., 0.;],
+                               [0., 0.;
+                                0., 1.;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    let coherent_information = sim
+        .coherent_information(&dephasing, &[0])
+        .expect("coherent information should be computed");
+    assert_approx_eq(0., coherent_information);
+}
+
+#[test]
+fn check_instrument_probabilities_do_not_modify_state() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let state_before = sim.state().expect("state should be valid").data().clone();
+
+    let probabilities = sim
+        .instrument_probabilities(&mz, &[0])
+        .expect("probabilities should be computed");
+    assert_eq!(2, probabilities.len());
+    assert_approx_eq(0.5, probabilities[0]);
+    assert_approx_eq(0.5, probabilities[1]);
+    let probabilities = sim
+        .instrument_probabilities(&mz, &[1])
+        .expect("probabilities should be computed");
+    assert_approx_eq(1., probabilities[0]);
+    assert_approx_eq(0., probabilities[1]);
+
+    let state_after = sim.state().expect("state should be valid").data();
+    assert_eq!(&state_before, state_after);
+    assert_approx_eq(1., sim.trace_change().expect("state should be valid"));
+}
+
+#[test]
+fn check_instrument_probabilities_fail_on_invalid_simulator() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new(1);
+    sim.set_min_trace_change(0.9);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(mz.operation(0), &[0])
+        .expect_err("simulation should be aborted");
+    assert!(matches!(
+        sim.instrument_probabilities(&mz, &[0]),
+        Err(Error::TraceBelowThreshold(_))
+    ));
+}
+
+#[test]
+fn check_sample_instrument_shots_histogram_converges_to_probabilities() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let t = operation!([1., 0.;
+                        0., Complex::from_polar(1., std::f64::consts::FRAC_PI_4);])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new_with_seed(1, 42);
+    // H T H yields a biased superposition.
+    for operation in [&h, &t, &h] {
+        sim.apply_operation(operation, &[0])
+            .expect("operation should succeed");
+    }
+    let state_before = sim.state().expect("state should be valid").data().clone();
+
+    let shots = 10_000;
+    let histogram = sim
+        .sample_instrument_shots(&mz, &[0], shots)
+        .expect("sampling should succeed");
+    let probabilities = sim
+        .instrument_probabilities(&mz, &[0])
+        .expect("probabilities should be computed");
+    assert_eq!(shots, histogram.iter().sum::<usize>());
+    for (count, probability) in histogram.iter().zip(probabilities) {
+        #[allow(clippy::cast_precision_loss)]
+        let (frequency, shots) = (*count as f64, shots as f64);
+        let standard_deviation = (shots * probability * (1. - probability)).sqrt();
+        assert!((frequency - shots * probability).abs() < 5. * standard_deviation);
+    }
+
+    let state_after = sim.state().expect("state should be valid").data();
+    assert_eq!(&state_before, state_after);
+}
+
+#[test]
+fn check_soft_reset_recovers_from_probability_zero_event() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new(1);
+    assert!(sim.is_valid());
+
+    // Projecting |0⟩ onto |1⟩ is a probability-0 event.
+    assert_eq!(
+        sim.apply_operation(mz.operation(1), &[0]),
+        Err(Error::ProbabilityZeroEvent)
+    );
+    assert!(!sim.is_valid());
+    assert_eq!(
+        sim.apply_operation(&h, &[0]),
+        Err(Error::ProbabilityZeroEvent)
+    );
+
+    sim.soft_reset();
+    assert!(sim.is_valid());
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid").data();
+    assert_approx_eq(FRAC_1_SQRT_2, state[0].re);
+    assert_approx_eq(FRAC_1_SQRT_2, state[1].re);
+}
+
+#[test]
+fn check_reset_recovers_from_probability_zero_event_and_clears_stats() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(mz.operation(0), &[0])
+        .expect("operation should succeed");
+
+    // Projecting |0⟩ onto |1⟩ is a probability-0 event.
+    assert_eq!(
+        sim.apply_operation(mz.operation(1), &[0]),
+        Err(Error::ProbabilityZeroEvent)
+    );
+    assert!(!sim.is_valid());
+
+    sim.reset();
+    assert!(sim.is_valid());
+    assert_eq!(ShotStats::default(), sim.shot_stats());
+    assert_approx_eq(1., sim.trace_change().expect("trace should be valid"));
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid").data();
+    assert_approx_eq(FRAC_1_SQRT_2, state[0].re);
+    assert_approx_eq(FRAC_1_SQRT_2, state[1].re);
+}
+
+#[test]
+fn check_run_parameterized_batch_runs_each_parameter_set_from_current_state() {
+    let h = noiseless_h();
+    let ry = |parameters: &[f64]| {
+        let (sin, cos) = (parameters[0] / 2.).sin_cos();
+        operation!([cos, -sin;
+                    sin,  cos;])
+        .expect("operation should be valid")
+    };
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    let state_before = sim.state().expect("state should be valid").data().clone();
+
+    let angles = [0., std::f64::consts::FRAC_PI_2, std::f64::consts::PI];
+    let parameter_sets: Vec<Vec<f64>> = angles.iter().map(|angle| vec![*angle]).collect();
+    let final_states = sim
+        .run_parameterized_batch(
+            |sim, parameters| sim.apply_operation(&ry(parameters), &[0]),
+            &parameter_sets,
+        )
+        .expect("batch should succeed");
+
+    assert_eq!(angles.len(), final_states.len());
+    for (angle, state) in angles.iter().zip(&final_states) {
+        // Qubit 1 stays in |+⟩ while qubit 0 is rotated to cos(θ/2)|0⟩ + sin(θ/2)|1⟩.
+        let (sin, cos) = (angle / 2.).sin_cos();
+        let expected = [
+            cos * FRAC_1_SQRT_2,
+            sin * FRAC_1_SQRT_2,
+            cos * FRAC_1_SQRT_2,
+            sin * FRAC_1_SQRT_2,
+        ];
+        for (l, r) in expected.iter().zip(state.data().iter()) {
+            assert_approx_eq(*l, r.re);
+            assert_approx_eq(0., r.im);
+        }
+    }
+
+    let state_after = sim.state().expect("state should be valid").data();
+    assert_eq!(&state_before, state_after);
+}
+
+#[test]
+fn check_parameter_shift_gradient_of_ry_rotation() {
+    let ry = |parameters: &[f64]| {
+        let (sin, cos) = (parameters[0] / 2.).sin_cos();
+        operation!([cos, -sin;
+                    sin,  cos;])
+        .expect("operation should be valid")
+    };
+    let z: SquareMatrix = dmatrix![1., 0.; 0., -1.].map(Complex::from);
+    let mut sim = StateVectorSimulator::new(1);
+
+    // ⟨Z⟩ = cos(θ) after Ry(θ)|0⟩, so its derivative is -sin(θ).
+    let theta = 0.3_f64;
+    let gradient = sim
+        .parameter_shift_gradient(
+            |sim, parameters| sim.apply_operation(&ry(parameters), &[0]),
+            &[theta],
+            &z,
+            &[0],
+            0,
+        )
+        .expect("gradient should be computed");
+    assert_approx_eq(-theta.sin(), gradient);
+
+    assert_eq!(
+        sim.parameter_shift_gradient(
+            |sim, parameters| sim.apply_operation(&ry(parameters), &[0]),
+            &[theta],
+            &z,
+            &[0],
+            1,
+        ),
+        Err(Error::ParameterIndexOutOfBounds(1))
+    );
+}
+
+#[test]
+fn check_fidelity_and_trace_distance_of_identical_states() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+    assert_approx_eq(
+        1.,
+        state.fidelity(state).expect("fidelity should be computed"),
+    );
+    assert_approx_eq(
+        0.,
+        state
+            .trace_distance(state)
+            .expect("trace distance should be computed"),
+    );
+}
+
+#[test]
+fn check_fidelity_and_trace_distance_of_orthogonal_states() {
+    let (h, z) = (
+        noiseless_h(),
+        operation!([1., 0.;
+                    0., -1.;])
+        .expect("operation should be valid"),
+    );
+    let mut plus_sim = StateVectorSimulator::new(1);
+    plus_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let mut minus_sim = StateVectorSimulator::new(1);
+    minus_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    minus_sim
+        .apply_operation(&z, &[0])
+        .expect("operation should succeed");
+
+    let plus = plus_sim.state().expect("state should be valid");
+    let minus = minus_sim.state().expect("state should be valid");
+    assert_approx_eq(
+        0.,
+        plus.fidelity(minus).expect("fidelity should be computed"),
+    );
+    assert_approx_eq(
+        1.,
+        plus.trace_distance(minus)
+            .expect("trace distance should be computed"),
+    );
+
+    let two_qubits = StateVectorSimulator::new(2);
+    assert!(matches!(
+        plus.fidelity(two_qubits.state().expect("state should be valid")),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn check_apply_unitary_matches_single_kraus_operation() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let s: SquareMatrix = dmatrix![Complex::ONE, Complex::ZERO; Complex::ZERO, Complex::I];
+    let h_matrix =
+        dmatrix![FRAC_1_SQRT_2, FRAC_1_SQRT_2; FRAC_1_SQRT_2, -FRAC_1_SQRT_2].map(Complex::from);
+    let cnot_matrix = dmatrix![
+        1., 0., 0., 0.;
+        0., 1., 0., 0.;
+        0., 0., 0., 1.;
+        0., 0., 1., 0.;
+    ]
+    .map(Complex::from);
+    let s_operation = Operation::new(vec![s.clone()]).expect("operation should be valid");
+
+    let mut operation_sim = StateVectorSimulator::new(2);
+    operation_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    operation_sim
+        .apply_operation(&s_operation, &[0])
+        .expect("operation should succeed");
+    operation_sim
+        .apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+
+    let mut unitary_sim = StateVectorSimulator::new(2);
+    unitary_sim
+        .apply_unitary(&h_matrix, &[0])
+        .expect("unitary should be applied");
+    unitary_sim
+        .apply_unitary(&s, &[0])
+        .expect("unitary should be applied");
+    unitary_sim
+        .apply_unitary(&cnot_matrix, &[1, 0])
+        .expect("unitary should be applied");
+
+    let operation_state = operation_sim.state().expect("state should be valid");
+    let unitary_state = unitary_sim.state().expect("state should be valid");
+    for (l, r) in operation_state
+        .data()
+        .iter()
+        .zip(unitary_state.data().iter())
+    {
+        assert_approx_eq(l.re, r.re);
+        assert_approx_eq(l.im, r.im);
+    }
+    assert_approx_eq(1., unitary_state.trace_change());
+
+    assert!(matches!(
+        unitary_sim.apply_unitary(&h_matrix, &[0, 1]),
+        Err(Error::MatrixVecDimensionMismatch { .. })
+    ));
+}
+
+#[test]
+fn check_apply_operation_projected_reports_leakage_in_trace_change() {
+    let h = noiseless_h();
+    // Projector onto the subspace spanned by |00⟩ and |11⟩.
+    let projector: SquareMatrix = dmatrix![
+        1., 0., 0., 0.;
+        0., 0., 0., 0.;
+        0., 0., 0., 0.;
+        0., 0., 0., 1.;
+    ]
+    .map(Complex::from);
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation_projected(&h, &[1], &projector)
+        .expect("operation should succeed");
+
+    // H ⊗ H |00⟩ has half of its weight outside the subspace.
+    assert_approx_eq(0.5, sim.trace_change().expect("state should be valid"));
+    let state = sim.state().expect("state should be valid").data();
+    let expected = [FRAC_1_SQRT_2, 0., 0., FRAC_1_SQRT_2];
+    for (l, r) in expected.iter().zip(state.iter()) {
+        assert_approx_eq(*l, r.re);
+        assert_approx_eq(0., r.im);
+    }
+}
+
+#[test]
+fn check_apply_operation_projected_fails_on_invalid_projector() {
+    let h = noiseless_h();
+    let not_idempotent: SquareMatrix = dmatrix![0.5, 0.; 0., 1.].map(Complex::from);
+    let mut sim = StateVectorSimulator::new(1);
+    assert!(matches!(
+        sim.apply_operation_projected(&h, &[0], &not_idempotent),
+        Err(Error::InvalidProjector(_))
+    ));
+    let wrong_dimensions = SquareMatrix::identity(4, 4);
+    assert!(matches!(
+        sim.apply_operation_projected(&h, &[0], &wrong_dimensions),
+        Err(Error::InvalidProjector(_))
+    ));
+}
+
+#[test]
+fn check_apply_operation_raw_can_skip_renormalization() {
+    let h = noiseless_h();
+    let mz0 = operation!([1., 0.;
+                          0., 0.;])
+    .expect("operation should be valid");
+
+    let mut raw_sim = StateVectorSimulator::new(1);
+    raw_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    raw_sim
+        .apply_operation_raw(&mz0, &[0], false)
+        .expect("operation should succeed");
+    let raw_state = raw_sim.state().expect("state should be valid");
+    assert_approx_eq(0.5, raw_state.data().norm_squared());
+    assert_approx_eq(0.5, raw_state.trace_change());
+
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&mz0, &[0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+    assert_approx_eq(1.0, state.data().norm_squared());
+    assert_approx_eq(0.5, state.trace_change());
+}
+
+#[test]
+fn check_operations_after_apply_operation_raw_dont_count_its_norm_twice() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mz0 = operation!([1., 0.;
+                          0., 0.;])
+    .expect("operation should be valid");
+
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation_raw(&mz0, &[0], false)
+        .expect("operation should succeed");
+    sim.apply_operation_raw(&h, &[0], false)
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+    assert_approx_eq(0.5, state.data().norm_squared());
+    assert_approx_eq(0.5, state.trace_change());
+
+    // The state is now |+⟩, so measuring |0⟩ has probability 1/2.
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], 0.25)
+        .expect("instrument should succeed");
+    assert_eq!(0, outcome);
+    let state = sim.state().expect("state should be valid");
+    assert_approx_eq(1.0, state.data().norm_squared());
+    assert_approx_eq(0.25, state.trace_change());
+
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    assert_approx_eq(
+        0.25,
+        sim.state().expect("state should be valid").trace_change(),
+    );
+}
+
+#[test]
+fn check_hilbert_schmidt_distance_of_orthogonal_states_is_two() {
+    let h = noiseless_h();
+    let zero_sim = StateVectorSimulator::new(1);
+    let mut plus_sim = StateVectorSimulator::new(1);
+    plus_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let zero = zero_sim.state().expect("state should be valid");
+    let plus = plus_sim.state().expect("state should be valid");
+
+    assert_approx_eq(
+        0.,
+        zero.hilbert_schmidt_distance(zero)
+            .expect("distance should be computed"),
+    );
+    assert_approx_eq(
+        1.,
+        zero.hilbert_schmidt_distance(plus)
+            .expect("distance should be computed"),
+    );
+}
+
+#[test]
+fn check_basis_state_probabilities_of_bell_pair() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    let state = sim.state().expect("state should be valid");
+
+    for (l, r) in [0.5, 0., 0., 0.5].iter().zip(state.populations()) {
+        assert_approx_eq(*l, r);
+    }
+    assert_approx_eq(
+        0.5,
+        state
+            .basis_state_probability(0b11)
+            .expect("basis index should be valid"),
+    );
+    assert_eq!(
+        state.basis_state_probability(4),
+        Err(Error::BasisIndexOutOfBounds(4, 4))
+    );
+}
+
+#[test]
+fn check_into_density_matrix_simulator_preserves_state() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+    sim.set_trace(0.25).expect("trace should be set");
+
+    let dm_sim = sim
+        .into_density_matrix_simulator()
+        .expect("conversion should succeed");
+    let state = dm_sim.state().expect("state should be valid");
+    assert_eq!(2, state.number_of_qubits());
+    assert_approx_eq(0.25, state.trace_change());
+    // |Φ⁺⟩⟨Φ⁺| has entries 0.5 in its four corners.
+    let expected = [
+        0.5, 0., 0., 0.5, //
+        0., 0., 0., 0., //
+        0., 0., 0., 0., //
+        0.5, 0., 0., 0.5,
+    ];
+    for (l, r) in expected.iter().zip(state.data().iter()) {
+        assert_approx_eq(*l, r.re);
+        assert_approx_eq(0., r.im);
+    }
+}
+
+#[test]
+fn check_deallocate_qubit_removes_unentangled_ancilla() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+
+    let ancilla = sim.allocate_qubit().expect("allocation should succeed");
+    assert_eq!(2, ancilla);
+    sim.apply_operation(&h, &[ancilla])
+        .expect("operation should succeed");
+    // Qubit 0 is entangled with qubit 1.
+    assert_eq!(sim.deallocate_qubit(0), Err(Error::QubitIsEntangled(0)));
+    sim.deallocate_qubit(ancilla)
+        .expect("deallocation should succeed");
+
+    let state = sim.state().expect("state should be valid");
+    assert_eq!(2, state.number_of_qubits());
+    let expected = [FRAC_1_SQRT_2, 0., 0., FRAC_1_SQRT_2];
+    for (l, r) in expected.iter().zip(state.data().iter()) {
+        assert_approx_eq(*l, r.re);
+        assert_approx_eq(0., r.im);
+    }
+}
+
+#[test]
+fn check_deallocate_qubit_uses_simulator_tolerance() {
+    // The qubits are barely entangled: the purity of each reduced state is 1 - 2e-9.
+    let small_probability: f64 = 1e-9;
+    let state = || {
+        StateVector::try_from(
+            4,
+            2,
+            1.0,
+            dvector![
+                (1.0 - small_probability).sqrt(),
+                0.,
+                0.,
+                small_probability.sqrt()
+            ]
+            .map(Complex::from),
+        )
+        .expect("state should be valid")
+    };
+
+    let mut sim = StateVectorSimulator::new(2);
+    sim.set_state(state()).expect("state should be set");
+    assert_eq!(sim.deallocate_qubit(1), Err(Error::QubitIsEntangled(1)));
+
+    let mut sim = StateVectorSimulator::new(2).with_tolerance(1e-6);
+    sim.set_state(state()).expect("state should be set");
+    sim.deallocate_qubit(1)
+        .expect("deallocation should succeed");
+    assert_eq!(
+        1,
+        sim.state()
+            .expect("state should be valid")
+            .number_of_qubits()
+    );
+}
+
+#[test]
+fn check_deallocate_qubit_renumbers_higher_qubits() {
+    let x = operation!([0., 1.;
+                        1., 0.;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(3);
+    sim.apply_operation(&x, &[2])
+        .expect("operation should succeed");
+    sim.deallocate_qubit(1)
+        .expect("deallocation should succeed");
+
+    // The qubit in the |1⟩ state now has id 1.
+    let state = sim.state().expect("state should be valid");
+    for (l, r) in [0., 0., 1., 0.].iter().zip(state.populations()) {
+        assert_approx_eq(*l, r);
+    }
+}
+
+#[test]
+fn check_strict_sampling_reports_negative_rescaled_sample() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    // A random sample slightly above 1 is accepted within `TOLERANCE`, but the rescaled
+    // sample for the last outcome, which has probability 0.5, ends up below `-TOLERANCE`.
+    let random_sample = 1.0 + 0.9 * TOLERANCE;
+
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], random_sample)
+        .expect("instrument should succeed");
+    assert_eq!(1, outcome);
+
+    let mut strict_sim = StateVectorSimulator::new(1);
+    strict_sim.set_strict_sampling(true);
+    strict_sim
+        .apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    assert!(matches!(
+        strict_sim.sample_instrument_with_distribution(&mz, &[0], random_sample),
+        Err(Error::NegativeRescaledSample(_))
+    ));
+    assert!(strict_sim.is_valid());
+}
+
+#[test]
+fn check_snapshot_can_be_restored() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let snapshot = sim.snapshot().expect("state should be valid");
+
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], 0.25)
+        .expect("instrument should succeed");
+    assert_eq!(0, outcome);
+    sim.set_state(snapshot).expect("state should be restored");
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], 0.75)
+        .expect("instrument should succeed");
+    assert_eq!(1, outcome);
+}
+
+#[test]
+fn check_snapshot_matches_state_after_restore() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let snapshot = sim.snapshot().expect("state should be valid");
+    let expected = snapshot.data().clone();
+
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    sim.set_state(snapshot).expect("state should be restored");
+    assert_eq!(
+        &expected,
+        sim.state().expect("state should be valid").data()
+    );
+}
+
+#[test]
+fn check_cloned_simulators_evolve_independently() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let mut fork = sim.clone();
+
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], 0.25)
+        .expect("instrument should succeed");
+    assert_eq!(0, outcome);
+    let outcome = fork
+        .sample_instrument_with_distribution(&mz, &[0], 0.75)
+        .expect("instrument should succeed");
+    assert_eq!(1, outcome);
+
+    let populations = sim.state().expect("state should be valid").populations();
+    assert_approx_eq(1., populations[0]);
+    let populations = fork.state().expect("state should be valid").populations();
+    assert_approx_eq(1., populations[1]);
+}
+
+#[test]
+fn check_cloned_simulator_replays_the_same_samples() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new_with_seed(1, 42);
+    let mut clone = sim.clone();
+    let outcomes = |sim: &mut StateVectorSimulator| -> Vec<usize> {
+        (0..32)
+            .map(|_| {
+                sim.apply_operation(&h, &[0])
+                    .expect("operation should succeed");
+                sim.sample_instrument(&noiseless_mz(), &[0])
+                    .expect("instrument should succeed")
+            })
+            .collect()
+    };
+    assert_eq!(outcomes(&mut sim), outcomes(&mut clone));
+}
+
+#[test]
+fn check_forked_simulator_draws_independent_samples() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new_with_seed(1, 42);
+    let mut fork = sim.fork();
+    let outcomes = |sim: &mut StateVectorSimulator| -> Vec<usize> {
+        (0..32)
+            .map(|_| {
+                sim.apply_operation(&h, &[0])
+                    .expect("operation should succeed");
+                sim.sample_instrument(&noiseless_mz(), &[0])
+                    .expect("instrument should succeed")
+            })
+            .collect()
+    };
+    assert_ne!(outcomes(&mut sim), outcomes(&mut fork));
+}
+
+#[test]
+fn check_successive_forks_draw_different_samples() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new_with_seed(1, 42);
+    let mut first_fork = sim.fork();
+    let mut second_fork = sim.fork();
+    let outcomes = |sim: &mut StateVectorSimulator| -> Vec<usize> {
+        (0..32)
+            .map(|_| {
+                sim.apply_operation(&h, &[0])
+                    .expect("operation should succeed");
+                sim.sample_instrument(&noiseless_mz(), &[0])
+                    .expect("instrument should succeed")
+            })
+            .collect()
+    };
+    assert_ne!(outcomes(&mut first_fork), outcomes(&mut second_fork));
+}
+
+#[test]
+fn check_fork_advances_the_original_generator() {
+    let h = noiseless_h();
+    let mut sim = StateVectorSimulator::new_with_seed(1, 42);
+    let mut unforked_sim = StateVectorSimulator::new_with_seed(1, 42);
+    let mut fork = sim.fork();
+    let outcomes = |sim: &mut StateVectorSimulator| -> Vec<usize> {
+        (0..32)
+            .map(|_| {
+                sim.apply_operation(&h, &[0])
+                    .expect("operation should succeed");
+                sim.sample_instrument(&noiseless_mz(), &[0])
+                    .expect("instrument should succeed")
+            })
+            .collect()
+    };
+    let sim_outcomes = outcomes(&mut sim);
+    assert_ne!(outcomes(&mut unforked_sim), sim_outcomes);
+    assert_ne!(outcomes(&mut fork), sim_outcomes);
+}
+
+#[test]
+fn check_apply_operation_broadcast_matches_sequential_application() {
+    let h = noiseless_h();
+    let depolarizing = crate::channels::depolarizing(0.5).expect("operation should be valid");
+    let groups = [vec![0], vec![2], vec![3]];
+    let mut broadcast_sim = StateVectorSimulator::new_with_seed(4, 42);
+    let mut sequential_sim = StateVectorSimulator::new_with_seed(4, 42);
+    for sim in [&mut broadcast_sim, &mut sequential_sim] {
+        for qubit in 0..4 {
+            sim.apply_operation(&h, &[qubit])
+                .expect("operation should succeed");
+        }
+    }
+
+    broadcast_sim
+        .apply_operation_broadcast(&depolarizing, &groups)
+        .expect("broadcast should succeed");
+    for qubits in &groups {
+        sequential_sim
+            .apply_operation(&depolarizing, qubits)
+            .expect("operation should succeed");
+    }
+    assert_eq!(
+        sequential_sim
+            .state()
+            .expect("state should be valid")
+            .data(),
+        broadcast_sim.state().expect("state should be valid").data()
+    );
+}
+
+#[test]
+fn check_apply_operation_broadcast_rejects_invalid_groups() {
+    let cnot = noiseless_cnot();
+    let mut sim = StateVectorSimulator::new(4);
+    assert_eq!(
+        sim.apply_operation_broadcast(&cnot, &[vec![0, 1], vec![1, 2]]),
+        Err(Error::DuplicateQubit(1))
+    );
+    assert_eq!(
+        sim.apply_operation_broadcast(&cnot, &[vec![0, 1], vec![2]]),
+        Err(Error::MatrixVecDimensionMismatch {
+            nrows: 4,
+            ncols: 4,
+            vec_dim: 2,
+        })
+    );
+    let populations = sim.state().expect("state should be valid").populations();
+    assert_approx_eq(1., populations[0]);
+}
+
+#[test]
+fn check_apply_operation_broadcast_rejects_invalid_groups_without_growing() {
+    let cnot = noiseless_cnot();
+    let mut sim = StateVectorSimulator::new(2);
+    sim.set_auto_grow(true);
+    assert_eq!(
+        sim.apply_operation_broadcast(&cnot, &[vec![0, 1], vec![4]]),
+        Err(Error::MatrixVecDimensionMismatch {
+            nrows: 4,
+            ncols: 4,
+            vec_dim: 2,
+        })
+    );
+    assert_eq!(
+        sim.apply_operation_broadcast(&cnot, &[vec![0, 4], vec![4, 5]]),
+        Err(Error::DuplicateQubit(4))
+    );
+    let state = sim.state().expect("state should be valid");
+    assert_eq!(2, state.number_of_qubits());
+    assert_approx_eq(1., state.populations()[0]);
+}
+
+#[test]
+fn check_measurement_record_probability_of_bell_pair() {
+    let (h, cnot, mz) = (noiseless_h(), noiseless_cnot(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new(2);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&cnot, &[1, 0])
+        .expect("operation should succeed");
+
+    let record = |first, second| {
+        sim.measurement_record_probability(&[
+            (noiseless_mz(), vec![0], first),
+            (noiseless_mz(), vec![1], second),
+        ])
+        .expect("probability should be computed")
+    };
+    assert_approx_eq(0.5, record(0, 0));
+    assert_approx_eq(0.0, record(0, 1));
+    assert_approx_eq(0.0, record(1, 0));
+    assert_approx_eq(0.5, record(1, 1));
+
+    // The state of the simulator is not modified.
+    assert_approx_eq(
+        0.5,
+        sim.state()
+            .expect("state should be valid")
+            .basis_state_probability(0b11)
+            .expect("basis index should be valid"),
+    );
+    assert_eq!(
+        sim.measurement_record_probability(&[(mz, vec![0], 2)]),
+        Err(Error::InstrumentOutcomeOutOfBounds(2))
+    );
+}
+
+#[test]
+fn check_density_matrix_of_plus_i_state() {
+    let h = noiseless_h();
+    let s = operation!([1., 0.;
+                        0., Complex::I;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(1);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&s, &[0])
+        .expect("operation should succeed");
+
+    // |+i⟩⟨+i| = ½ [1, -i; i, 1].
+    let expected = dmatrix![
+        Complex::new(0.5, 0.), Complex::new(0., -0.5);
+        Complex::new(0., 0.5), Complex::new(0.5, 0.);
+    ];
+    assert_matrix_approx_eq(
+        &expected,
+        &sim.state().expect("state should be valid").density_matrix(),
+    );
+}
+
+#[test]
+fn check_trace_observer_reports_sampled_kraus_operators() {
+    use std::sync::{Arc, Mutex};
+
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut sim = StateVectorSimulator::new(2);
+    let observed_events = Arc::clone(&events);
+    sim.set_trace_observer(Box::new(move |event| {
+        observed_events
+            .lock()
+            .expect("lock should not be poisoned")
+            .push((
+                event.qubits.to_vec(),
+                event.kraus_index,
+                event.outcome,
+                event.trace_change,
+            ));
+    }));
+
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    sim.sample_instrument_with_distribution(&mz, &[1], 0.75)
+        .expect("instrument should succeed");
+    sim.clear_trace_observer();
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+
+    let events = events.lock().expect("lock should not be poisoned");
+    assert_eq!(2, events.len());
+    assert_eq!(
+        (vec![1], 0, None),
+        (events[0].0.clone(), events[0].1, events[0].2)
+    );
+    assert_approx_eq(1.0, events[0].3);
+    assert_eq!(
+        (vec![1], 0, Some(1)),
+        (events[1].0.clone(), events[1].1, events[1].2)
+    );
+    assert_approx_eq(0.5, events[1].3);
+}
+
+#[test]
+fn check_sample_instrument_shots_with_stats_reports_trajectory_cost() {
+    let (h, mz) = (noiseless_h(), noiseless_mz());
+    let mut sim = StateVectorSimulator::new_with_seed(1, 42);
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    let stats_before = sim.shot_stats();
+    assert_ne!(ShotStats::default(), stats_before);
+
+    let results = sim
+        .sample_instrument_shots_with_stats(&mz, &[0], 10)
+        .expect("shots should succeed");
+    assert_eq!(10, results.len());
+    for (outcome, stats) in results {
+        // Sampling outcome 1 evaluates the effect of outcome 0 first. Each outcome of `mz`
+        // has a single Kraus operator.
+        assert_eq!(
+            ShotStats {
+                kraus_samples: 1,
+                renormalizations: 1,
+                probability_evaluations: outcome + 2,
+            },
+            stats
+        );
+    }
+    // The stats of the simulator are restored after the shots.
+    assert_eq!(stats_before, sim.shot_stats());
+    sim.soft_reset();
+    assert_eq!(stats_before, sim.shot_stats());
+    sim.reset_stats();
+    assert_eq!(ShotStats::default(), sim.shot_stats());
+}
+
+#[test]
+fn check_measure_in_x_basis_of_plus_state_yields_zero() {
+    let h = noiseless_h();
+    let h_matrix: SquareMatrix = dmatrix![
+        FRAC_1_SQRT_2, FRAC_1_SQRT_2;
+        FRAC_1_SQRT_2, -FRAC_1_SQRT_2;
+    ]
+    .map(Complex::from);
+    for seed in 0..20 {
+        let mut sim = StateVectorSimulator::new_with_seed(1, seed);
+        sim.apply_operation(&h, &[0])
+            .expect("operation should succeed");
+        let outcome = sim
+            .measure_in_basis(0, &h_matrix)
+            .expect("measurement should succeed");
+        assert_eq!(0, outcome);
+
+        // The qubit is rotated back into the |+⟩ state.
+        let state = sim.state().expect("state should be valid").data();
+        for r in state.iter() {
+            assert_approx_eq(FRAC_1_SQRT_2, r.re);
+            assert_approx_eq(0., r.im);
+        }
+        assert_approx_eq(1., sim.trace_change().expect("state should be valid"));
+    }
+
+    let mut sim = StateVectorSimulator::new(1);
+    let not_unitary: SquareMatrix = dmatrix![1., 1.; 0., 1.].map(Complex::from);
+    assert!(matches!(
+        sim.measure_in_basis(0, &not_unitary),
+        Err(Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn check_log_trace_change_does_not_underflow() {
+    let half_identity = operation!([FRAC_1_SQRT_2, 0.;
+                                    0., FRAC_1_SQRT_2;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(1);
+    for _ in 0..10_000 {
+        sim.apply_operation(&half_identity, &[0])
+            .expect("operation should succeed");
+    }
+    assert_approx_eq(0.0, sim.trace_change().expect("state should be valid"));
+    let log_trace_change = sim.log_trace_change().expect("state should be valid");
+    assert!((log_trace_change - 10_000.0 * 0.5_f64.ln()).abs() < 1e-6);
+}
+
+#[test]
+fn check_apply_swap_network_permutes_qubits() {
+    let h = noiseless_h();
+    let x = operation!([0., 1.;
+                        1., 0.;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(3);
+    sim.apply_operation(&x, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    // Qubit 0 moves to 2 and then to 1, while qubit 1 moves to 2.
+    sim.apply_swap_network(&[(0, 2), (2, 1)])
+        .expect("swaps should succeed");
+
+    let state = sim.state().expect("state should be valid");
+    for (l, r) in [0., 0., 0.5, 0., 0., 0., 0.5, 0.]
+        .iter()
+        .zip(state.populations())
+    {
+        assert_approx_eq(*l, r);
+    }
+
+    assert_eq!(
+        sim.apply_swap_network(&[(1, 1)]),
+        Err(Error::DuplicateQubit(1))
+    );
+    assert_eq!(
+        sim.apply_swap_network(&[(0, 3)]),
+        Err(Error::QubitIndexOutOfRange {
+            index: 3,
+            number_of_qubits: 3,
+        })
+    );
+}
+
+#[test]
+fn check_apply_swap_network_rejects_invalid_swaps_without_permuting() {
+    let x = operation!([0., 1.;
+                        1., 0.;])
+    .expect("operation should be valid");
+    let mut sim = StateVectorSimulator::new(3);
+    sim.apply_operation(&x, &[0])
+        .expect("operation should succeed");
+    assert_eq!(
+        sim.apply_swap_network(&[(0, 1), (2, 2)]),
+        Err(Error::DuplicateQubit(2))
+    );
+    assert_eq!(
+        sim.apply_swap_network(&[(0, 1), (1, 3)]),
+        Err(Error::QubitIndexOutOfRange {
+            index: 3,
+            number_of_qubits: 3,
+        })
+    );
+
+    sim.set_auto_grow(true);
+    assert_eq!(
+        sim.apply_swap_network(&[(0, 4), (3, 3)]),
+        Err(Error::DuplicateQubit(3))
+    );
+
+    // The qubit in the |1⟩ state didn't move and the system didn't grow.
+    let state = sim.state().expect("state should be valid");
+    assert_eq!(3, state.number_of_qubits());
+    assert_approx_eq(1., state.populations()[1]);
+}
+
+#[test]
+fn check_with_tolerance_allows_sampling_small_branches() {
+    let mz = noiseless_mz();
+    let small_probability: f64 = 1e-13;
+    let state = || {
+        StateVector::try_from(
+            2,
+            1,
+            1.0,
+            dvector![(1.0 - small_probability).sqrt(), small_probability.sqrt()].map(Complex::from),
+        )
+        .expect("state should be valid")
+    };
+    let random_sample = 1.0 - small_probability / 10.0;
+
+    // With the default tolerance the small branch is treated as a probability-0 event.
+    let mut sim = StateVectorSimulator::new(1);
+    sim.set_state(state()).expect("state should be set");
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], random_sample)
+        .expect("instrument should succeed");
+    assert_eq!(0, outcome);
+
+    let mut sim = StateVectorSimulator::new(1).with_tolerance(1e-15);
+    sim.set_state(state()).expect("state should be set");
+    let outcome = sim
+        .sample_instrument_with_distribution(&mz, &[0], random_sample)
+        .expect("instrument should succeed");
+    assert_eq!(1, outcome);
+    let log_trace_change = sim.log_trace_change().expect("state should be valid");
+    assert!((log_trace_change - small_probability.ln()).abs() < 1e-6);
+}
+
+#[test]
+fn check_simulator_with_trace_observer_is_send() {
+    fn assert_send<T: Send>(_: &T) {}
+
+    let mut sim = StateVectorSimulator::new(1);
+    sim.set_trace_observer(Box::new(|_| {}));
+    assert_send(&sim);
+}
@@ -4,14 +4,41 @@
 use crate::{
     instrument::Instrument,
     operation::{operation, Operation},
-    NoisySimulator,
+    DensityMatrixSimulator, Error, NoisySimulator, StateVectorSimulator,
 };
 use num_complex::Complex;
 
 use super::assert_approx_eq;
 
+/// The configuration methods shared by the simulators, which are inherent methods rather than
+/// part of `NoisySimulator` so that implementors of the trait don't have to provide them.
+pub trait ConfigurableSimulator: NoisySimulator {
+    fn set_min_trace_change(&mut self, threshold: f64);
+    fn soft_reset(&mut self);
+}
+
+impl ConfigurableSimulator for StateVectorSimulator {
+    fn set_min_trace_change(&mut self, threshold: f64) {
+        StateVectorSimulator::set_min_trace_change(self, threshold);
+    }
+
+    fn soft_reset(&mut self) {
+        StateVectorSimulator::soft_reset(self);
+    }
+}
+
+impl ConfigurableSimulator for DensityMatrixSimulator {
+    fn set_min_trace_change(&mut self, threshold: f64) {
+        DensityMatrixSimulator::set_min_trace_change(self, threshold);
+    }
+
+    fn soft_reset(&mut self) {
+        DensityMatrixSimulator::soft_reset(self);
+    }
+}
+
 /// Returns an H gate.
-fn noiseless_h() -> Operation {
+pub(crate) fn noiseless_h() -> Operation {
     let f = 0.5_f64.sqrt();
     operation!([f,  f;
                 f, -f;])
@@ -19,7 +46,7 @@ fn noiseless_h() -> Operation {
 }
 
 /// Returns a CNOT gate.
-fn noiseless_cnot() -> Operation {
+pub(crate) fn noiseless_cnot() -> Operation {
     operation!([1., 0., 0., 0.;
                 0., 1., 0., 0.;
                 0., 0., 0., 1.;
@@ -42,7 +69,7 @@ fn noiseless_mz1() -> Operation {
 }
 
 /// Returns an MZ measurement.
-pub(super) fn noiseless_mz() -> Instrument {
+pub(crate) fn noiseless_mz() -> Instrument {
     Instrument::new(vec![noiseless_mz0(), noiseless_mz1()]).expect("instrument should be valid")
 }
 
@@ -323,3 +350,128 @@ pub fn check_alternating_mz_and_mx_yield_right_probabilities<NS: NoisySimulator>
         assert_approx_eq(prob, sim.trace_change().expect("state should be valid"));
     }
 }
+
+/// Check that dropping below the minimum trace change aborts the simulation.
+pub fn check_trace_change_below_threshold_aborts_simulation<NS: ConfigurableSimulator>() {
+    let (h, mz0) = (noiseless_h(), noiseless_mz0());
+    let mut sim = NS::new(2);
+    sim.set_min_trace_change(0.3);
+
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+
+    // The first projection halves the trace, which is still above the threshold.
+    sim.apply_operation(&mz0, &[0])
+        .expect("operation should succeed");
+    assert_approx_eq(0.5, sim.trace_change().expect("state should be valid"));
+
+    // The second projection pushes the trace below the threshold.
+    let err = sim
+        .apply_operation(&mz0, &[1])
+        .expect_err("operation should fail");
+    assert!(matches!(err, Error::TraceBelowThreshold(_)));
+
+    // The simulator should remain in the aborted state.
+    assert!(matches!(
+        sim.trace_change(),
+        Err(Error::TraceBelowThreshold(_))
+    ));
+}
+
+/// Check that applying operations through a qubit mask targets the set bits in ascending order.
+pub fn check_apply_operation_mask_targets_qubits_in_ascending_order<NS: NoisySimulator>() {
+    let (h, cnot, mz0) = (noiseless_h(), noiseless_cnot(), noiseless_mz0());
+    let mut sim = NS::new(2);
+
+    // Make a Bell Pair. Qubit 1 is the control of the CNOT, since it is the second target.
+    sim.apply_operation_mask(&h, 0b10)
+        .expect("operation should succeed");
+    sim.apply_operation_mask(&cnot, 0b11)
+        .expect("operation should succeed");
+
+    // Projecting both qubits of a Bell Pair on the mz0 direction yields a 0.5 trace.
+    sim.apply_operation_mask(&mz0, 0b01)
+        .expect("operation should succeed");
+    sim.apply_operation_mask(&mz0, 0b10)
+        .expect("operation should succeed");
+    assert_approx_eq(0.5, sim.trace_change().expect("state should be valid"));
+}
+
+/// Check that invalid qubit masks are rejected.
+pub fn check_apply_operation_mask_rejects_invalid_masks<NS: NoisySimulator>() {
+    let (h, cnot) = (noiseless_h(), noiseless_cnot());
+    let mut sim = NS::new(2);
+
+    assert_eq!(
+        sim.apply_operation_mask(&cnot, 0b01),
+        Err(Error::QubitMaskMismatch {
+            mask_qubits: 1,
+            operation_qubits: 2,
+        })
+    );
+    assert_eq!(
+        sim.apply_operation_mask(&h, 1 << 5),
+        Err(Error::QubitIndexOutOfRange {
+            index: 5,
+            number_of_qubits: 2,
+        })
+    );
+}
+
+/// Check that out-of-range and duplicated qubit indices are rejected.
+pub fn check_apply_operation_rejects_invalid_qubit_indices<NS: NoisySimulator>() {
+    let cnot = noiseless_cnot();
+    let mut sim = NS::new(2);
+    assert_eq!(
+        sim.apply_operation(&cnot, &[0, 2]),
+        Err(Error::QubitIndexOutOfRange {
+            index: 2,
+            number_of_qubits: 2,
+        })
+    );
+    assert_eq!(
+        sim.apply_operation(&cnot, &[1, 1]),
+        Err(Error::DuplicateQubit(1))
+    );
+}
+
+/// Check that a soft reset recovers the simulator and keeps its configuration.
+pub fn check_soft_reset_restores_ground_state_and_keeps_configuration<NS: ConfigurableSimulator>() {
+    let (h, mz0, mz) = (noiseless_h(), noiseless_mz0(), noiseless_mz());
+    let mut sim = NS::new(2);
+    sim.set_min_trace_change(0.3);
+
+    // Abort the simulation by pushing the trace below the threshold.
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    sim.apply_operation(&mz0, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&mz0, &[1])
+        .expect_err("operation should fail");
+
+    // The reset recovers the simulator in the ground state.
+    sim.soft_reset();
+    assert_approx_eq(1.0, sim.trace_change().expect("state should be valid"));
+    for qubit in 0..2 {
+        let measurement = sim
+            .sample_instrument_with_distribution(&mz, &[qubit], 0.99)
+            .expect("measurement should succeed");
+        assert_eq!(measurement, 0);
+    }
+
+    // The minimum trace change is still configured.
+    sim.apply_operation(&h, &[0])
+        .expect("operation should succeed");
+    sim.apply_operation(&h, &[1])
+        .expect("operation should succeed");
+    sim.apply_operation(&mz0, &[0])
+        .expect("operation should succeed");
+    let err = sim
+        .apply_operation(&mz0, &[1])
+        .expect_err("operation should fail");
+    assert!(matches!(err, Error::TraceBelowThreshold(_)));
+}